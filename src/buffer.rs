@@ -0,0 +1,312 @@
+// The data being edited, stored as a piece table.
+// The original data is never modified, inserted bytes are appended to the
+// add buffer. The content is described by a list of pieces, where each
+// piece points into one of those two buffers. This makes inserting and
+// removing depend on the amount of pieces and the size of the edit, and
+// not on the size of the file.
+// The original data is usually read lazily from the file, see src/pages.rs.
+
+use std::cmp;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::io::SeekFrom;
+use std::ops::Range;
+
+use super::Pages;
+
+// Size of the chunks used when the whole buffer needs to be scanned
+pub const CHUNK: usize = 1 << 20;
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+enum Source {
+    Original,
+    Added,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Piece {
+    source: Source,
+    start: usize,
+    len: usize,
+}
+
+enum Original {
+    Memory(Vec<u8>),
+    File(Pages),
+}
+
+impl Original {
+    fn len(&self) -> usize {
+        match self {
+            Original::Memory(data) => data.len(),
+            Original::File(pages) => pages.len(),
+        }
+    }
+    fn failed(&self) -> bool {
+        match self {
+            Original::Memory(_) => false,
+            Original::File(pages) => pages.failed(),
+        }
+    }
+    fn read_into(&self, pos: usize, len: usize, out: &mut Vec<u8>) {
+        match self {
+            Original::Memory(data) => out.extend_from_slice(&data[pos..pos + len]),
+            Original::File(pages) => pages.read_into(pos, len, out),
+        }
+    }
+}
+
+pub struct Buffer {
+    original: Original,
+    added: Vec<u8>,
+    pieces: Vec<Piece>,
+    len: usize,
+}
+
+impl Default for Buffer {
+    fn default() -> Self {
+        Buffer::from(Vec::new())
+    }
+}
+
+impl From<Vec<u8>> for Buffer {
+    fn from(original: Vec<u8>) -> Self {
+        Buffer::with_original(Original::Memory(original))
+    }
+}
+
+impl Buffer {
+    fn with_original(original: Original) -> Self {
+        let len = original.len();
+        let mut pieces = Vec::new();
+        if len > 0 {
+            pieces.push(Piece {
+                source: Source::Original,
+                start: 0,
+                len,
+            });
+        }
+        Buffer {
+            original,
+            added: Vec::new(),
+            pieces,
+            len,
+        }
+    }
+    // The data of the file is only loaded when it is accessed
+    pub fn open(file: File) -> io::Result<Self> {
+        Ok(Buffer::with_original(Original::File(Pages::new(file)?)))
+    }
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    // Parts of the file that could not be read are zeros, they must not be saved
+    pub fn check_read(&self) -> io::Result<()> {
+        match self.original.failed() {
+            true => Err(io::Error::other("Parts of the file could not be read")),
+            false => Ok(()),
+        }
+    }
+    // Appends len bytes of the piece, starting at offset within the piece
+    fn read_piece(&self, piece: &Piece, offset: usize, len: usize, out: &mut Vec<u8>) {
+        let start = piece.start + offset;
+        match piece.source {
+            Source::Original => self.original.read_into(start, len, out),
+            Source::Added => out.extend_from_slice(&self.added[start..start + len]),
+        }
+    }
+    pub fn get(&self, pos: usize) -> Option<u8> {
+        self.read(pos..pos + 1).first().copied()
+    }
+    // Copies the bytes of the range, parts out of range are left away
+    pub fn read(&self, range: Range<usize>) -> Vec<u8> {
+        let mut ret = Vec::new();
+        let mut offset = 0;
+        for piece in self.pieces.iter() {
+            if offset >= range.end {
+                break;
+            }
+            let start = cmp::max(range.start, offset);
+            let end = cmp::min(range.end, offset + piece.len);
+            if start < end {
+                self.read_piece(piece, start - offset, end - start, &mut ret);
+            }
+            offset += piece.len;
+        }
+        ret
+    }
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.read(0..self.len)
+    }
+    // Writes in chunks, so huge files never need to be in memory
+    pub fn write_range_to<W: Write>(&self, out: &mut W, range: Range<usize>) -> io::Result<()> {
+        let end = cmp::min(range.end, self.len);
+        let mut pos = range.start;
+        while pos < end {
+            let next = cmp::min(pos + CHUNK, end);
+            out.write_all(&self.read(pos..next))?;
+            pos = next;
+        }
+        Ok(())
+    }
+    // Writes the data back to the file it was loaded from. If the length did
+    // not change, only the modified parts get written.
+    pub fn save(&mut self, file: &mut File) -> io::Result<()> {
+        let loaded_from_file = matches!(self.original, Original::File(_));
+        if loaded_from_file && self.len == self.original.len() {
+            // Everything gets read before writing, as the pieces to write
+            // may still be loaded from the parts of the file we overwrite
+            let mut changes = Vec::new();
+            let mut offset = 0;
+            for piece in self.pieces.iter() {
+                if piece.source != Source::Original || piece.start != offset {
+                    let mut data = Vec::new();
+                    self.read_piece(piece, 0, piece.len, &mut data);
+                    changes.push((offset, data));
+                }
+                offset += piece.len;
+            }
+            self.check_read()?;
+            for (offset, data) in changes {
+                file.seek(SeekFrom::Start(offset as u64))?;
+                file.write_all(&data)?;
+            }
+        } else {
+            let data = self.to_vec();
+            self.check_read()?;
+            file.seek(SeekFrom::Start(0))?;
+            file.write_all(&data)?;
+            file.set_len(data.len() as u64)?;
+        }
+        file.flush()?;
+        // The file now holds the current data, so it becomes the original
+        *self = Buffer::open(file.try_clone()?)?;
+        Ok(())
+    }
+    // Ensures a piece starts at pos and returns the index of that piece
+    fn split_at(&mut self, pos: usize) -> usize {
+        let mut offset = 0;
+        for i in 0..self.pieces.len() {
+            if offset == pos {
+                return i;
+            }
+            let piece = self.pieces[i];
+            if pos < offset + piece.len {
+                let left = pos - offset;
+                self.pieces[i].len = left;
+                self.pieces.insert(
+                    i + 1,
+                    Piece {
+                        source: piece.source,
+                        start: piece.start + left,
+                        len: piece.len - left,
+                    },
+                );
+                return i + 1;
+            }
+            offset += piece.len;
+        }
+        self.pieces.len()
+    }
+    pub fn insert(&mut self, pos: usize, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+        let pos = cmp::min(pos, self.len);
+        let index = self.split_at(pos);
+        let start = self.added.len();
+        self.added.extend_from_slice(data);
+        self.len += data.len();
+        // Typing in insert mode appends to the previous insert, so we grow
+        // that piece instead of adding a new piece for every keystroke
+        if index > 0 {
+            let previous = &mut self.pieces[index - 1];
+            if previous.source == Source::Added && previous.start + previous.len == start {
+                previous.len += data.len();
+                return;
+            }
+        }
+        self.pieces.insert(
+            index,
+            Piece {
+                source: Source::Added,
+                start,
+                len: data.len(),
+            },
+        );
+    }
+    pub fn remove(&mut self, range: Range<usize>) {
+        let start = cmp::min(range.start, self.len);
+        let end = cmp::min(range.end, self.len);
+        if start >= end {
+            return;
+        }
+        let first = self.split_at(start);
+        let last = self.split_at(end);
+        self.pieces.drain(first..last);
+        self.len -= end - start;
+    }
+    // Replaces the range with data and returns the bytes that got replaced
+    pub fn splice(&mut self, range: Range<usize>, data: &[u8]) -> Vec<u8> {
+        let old = self.read(range.clone());
+        self.remove(range.clone());
+        self.insert(range.start, data);
+        old
+    }
+    // Calls find on overlapping chunks of the whole data, so a match of up
+    // to overlap + 1 bytes is never cut in half. Returns the absolute position.
+    pub fn find_in_chunks<F>(&self, overlap: usize, find: F) -> Option<usize>
+    where
+        F: FnMut(&[u8]) -> Option<usize>,
+    {
+        self.find_in_chunks_from(0, overlap, find)
+    }
+    // Same as find_in_chunks, but starts at the position from
+    pub fn find_in_chunks_from<F>(&self, from: usize, overlap: usize, mut find: F) -> Option<usize>
+    where
+        F: FnMut(&[u8]) -> Option<usize>,
+    {
+        if from > self.len {
+            return None;
+        }
+        let mut start = from;
+        loop {
+            let end = cmp::min(start + CHUNK + overlap, self.len);
+            let chunk = self.read(start..end);
+            if let Some(pos) = find(&chunk) {
+                return Some(start + pos);
+            }
+            if end == self.len {
+                return None;
+            }
+            start += CHUNK;
+        }
+    }
+    // Like find_in_chunks, but going backwards from end. Only matches
+    // starting before end count, rfind gets the chunk and the amount of
+    // positions in it where the match may start and returns the last one.
+    pub fn rfind_in_chunks<F>(&self, end: usize, overlap: usize, mut rfind: F) -> Option<usize>
+    where
+        F: FnMut(&[u8], usize) -> Option<usize>,
+    {
+        let mut end = cmp::min(end, self.len);
+        while end > 0 {
+            let start = end.saturating_sub(CHUNK);
+            let chunk = self.read(start..cmp::min(end + overlap, self.len));
+            if let Some(pos) = rfind(&chunk, end - start) {
+                return Some(start + pos);
+            }
+            end = start;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+#[path = "./buffer_test.rs"]
+mod buffer_test;
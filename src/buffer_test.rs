@@ -0,0 +1,164 @@
+use super::*;
+
+#[test]
+fn buffer_from_vec() {
+    let buf = Buffer::from(vec![0x01, 0x02, 0x03]);
+    assert_eq!(buf.len(), 3);
+    assert_eq!(buf.to_vec(), vec![0x01, 0x02, 0x03]);
+}
+#[test]
+fn buffer_empty() {
+    let buf = Buffer::from(Vec::new());
+    assert!(buf.is_empty());
+    assert_eq!(buf.get(0), None);
+    assert_eq!(buf.to_vec(), Vec::<u8>::new());
+}
+#[test]
+fn buffer_get() {
+    let mut buf = Buffer::from(vec![0x01, 0x02, 0x03]);
+    buf.insert(1, &[0xAA]);
+    assert_eq!(buf.get(0), Some(0x01));
+    assert_eq!(buf.get(1), Some(0xAA));
+    assert_eq!(buf.get(2), Some(0x02));
+    assert_eq!(buf.get(4), None);
+}
+#[test]
+fn buffer_insert_at_start() {
+    let mut buf = Buffer::from(vec![0x01, 0x02, 0x03]);
+    buf.insert(0, &[0xAA, 0xBB]);
+    assert_eq!(buf.to_vec(), vec![0xAA, 0xBB, 0x01, 0x02, 0x03]);
+}
+#[test]
+fn buffer_insert_in_middle() {
+    let mut buf = Buffer::from(vec![0x01, 0x02, 0x03]);
+    buf.insert(2, &[0xAA]);
+    assert_eq!(buf.to_vec(), vec![0x01, 0x02, 0xAA, 0x03]);
+}
+#[test]
+fn buffer_insert_at_end() {
+    let mut buf = Buffer::from(vec![0x01, 0x02, 0x03]);
+    buf.insert(3, &[0xAA]);
+    assert_eq!(buf.to_vec(), vec![0x01, 0x02, 0x03, 0xAA]);
+}
+#[test]
+fn buffer_insert_sequence_grows_one_piece() {
+    let mut buf = Buffer::from(vec![0x01, 0x02, 0x03]);
+    buf.insert(1, &[0xAA]);
+    buf.insert(2, &[0xBB]);
+    buf.insert(3, &[0xCC]);
+    assert_eq!(buf.to_vec(), vec![0x01, 0xAA, 0xBB, 0xCC, 0x02, 0x03]);
+    assert_eq!(buf.pieces.len(), 3);
+}
+#[test]
+fn buffer_remove_over_pieces() {
+    let mut buf = Buffer::from(vec![0x01, 0x02, 0x03, 0x04]);
+    buf.insert(2, &[0xAA, 0xBB]);
+    buf.remove(1..3);
+    assert_eq!(buf.to_vec(), vec![0x01, 0xBB, 0x03, 0x04]);
+    assert_eq!(buf.len(), 4);
+}
+#[test]
+fn buffer_remove_out_of_range() {
+    let mut buf = Buffer::from(vec![0x01, 0x02, 0x03]);
+    buf.remove(2..10);
+    assert_eq!(buf.to_vec(), vec![0x01, 0x02]);
+    buf.remove(5..10);
+    assert_eq!(buf.to_vec(), vec![0x01, 0x02]);
+}
+#[test]
+fn buffer_splice_returns_old() {
+    let mut buf = Buffer::from(vec![0x01, 0x02, 0x03]);
+    let old = buf.splice(1..2, &[0xAA, 0xBB]);
+    assert_eq!(old, vec![0x02]);
+    assert_eq!(buf.to_vec(), vec![0x01, 0xAA, 0xBB, 0x03]);
+}
+#[test]
+fn buffer_read_partial() {
+    let mut buf = Buffer::from(vec![0x01, 0x02, 0x03]);
+    buf.insert(3, &[0x04, 0x05]);
+    assert_eq!(buf.read(2..4), vec![0x03, 0x04]);
+    assert_eq!(buf.read(4..8), vec![0x05]);
+}
+#[test]
+fn buffer_write_all() {
+    let mut buf = Buffer::from(vec![0x01, 0x02, 0x03]);
+    buf.insert(1, &[0xAA]);
+    let mut out = Vec::new();
+    buf.write_range_to(&mut out, 0..buf.len()).unwrap();
+    assert_eq!(out, vec![0x01, 0xAA, 0x02, 0x03]);
+}
+#[test]
+fn buffer_find_in_chunks_across_chunk_border() {
+    let mut data = vec![0; CHUNK + 10];
+    data[CHUNK - 1] = 0xAA;
+    data[CHUNK] = 0xBB;
+    let buf = Buffer::from(data);
+    let found = buf.find_in_chunks(1, |chunk| chunk.windows(2).position(|w| w == [0xAA, 0xBB]));
+    assert_eq!(found, Some(CHUNK - 1));
+}
+#[test]
+fn buffer_find_in_chunks_from() {
+    let buf = Buffer::from(vec![0xAA, 0x00, 0xAA, 0x00]);
+    let find = |chunk: &[u8]| chunk.iter().position(|b| *b == 0xAA);
+    assert_eq!(buf.find_in_chunks_from(1, 0, find), Some(2));
+    assert_eq!(buf.find_in_chunks_from(3, 0, find), None);
+    assert_eq!(buf.find_in_chunks_from(5, 0, find), None);
+}
+#[test]
+fn buffer_rfind_in_chunks_across_chunk_border() {
+    let mut data = vec![0; 2 * CHUNK + 10];
+    data[5] = 0xAA;
+    data[CHUNK - 1] = 0xAA;
+    data[CHUNK] = 0xBB;
+    let buf = Buffer::from(data);
+    let rfind = |chunk: &[u8], starts: usize| {
+        chunk
+            .windows(2)
+            .take(starts)
+            .rposition(|w| w == [0xAA, 0xBB] || w == [0xAA, 0x00])
+    };
+    assert_eq!(buf.rfind_in_chunks(buf.len(), 1, rfind), Some(CHUNK - 1));
+    assert_eq!(buf.rfind_in_chunks(CHUNK - 1, 1, rfind), Some(5));
+    assert_eq!(buf.rfind_in_chunks(5, 1, rfind), None);
+}
+#[test]
+fn buffer_save_same_length() {
+    let path = std::env::temp_dir().join("hexdino_buffer_test_save_same_length");
+    std::fs::write(&path, [0x01, 0x02, 0x03, 0x04]).unwrap();
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)
+        .unwrap();
+    let mut buf = Buffer::open(file.try_clone().unwrap()).unwrap();
+    // Move the first byte to the end, all bytes change position
+    buf.remove(0..1);
+    buf.insert(3, &[0x01]);
+    buf.save(&mut file).unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), vec![0x02, 0x03, 0x04, 0x01]);
+    assert_eq!(buf.to_vec(), vec![0x02, 0x03, 0x04, 0x01]);
+    std::fs::remove_file(&path).unwrap();
+}
+#[test]
+fn buffer_save_other_length() {
+    let path = std::env::temp_dir().join("hexdino_buffer_test_save_other_length");
+    std::fs::write(&path, [0x01, 0x02, 0x03, 0x04]).unwrap();
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)
+        .unwrap();
+    let mut buf = Buffer::open(file.try_clone().unwrap()).unwrap();
+    buf.remove(1..3);
+    buf.save(&mut file).unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), vec![0x01, 0x04]);
+    std::fs::remove_file(&path).unwrap();
+}
+#[test]
+fn buffer_write_range_to() {
+    let mut buf = Buffer::from(vec![0x01, 0x02, 0x03]);
+    buf.insert(1, &[0xAA]);
+    let mut out = Vec::new();
+    buf.write_range_to(&mut out, 1..3).unwrap();
+    assert_eq!(out, vec![0xAA, 0x02]);
+}
@@ -0,0 +1,148 @@
+// Copying to the system clipboard, used by the register '+'.
+// By default the terminal is asked to do it with the OSC 52 escape
+// sequence, which also works over ssh. Alternatively a program like xclip
+// gets the text on its standard input.
+// Pasted text goes the other way, hex strings are decoded to bytes.
+
+use std::io;
+use std::io::prelude::*;
+use std::io::stdout;
+use std::process::{Command, Stdio};
+
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+pub enum ClipFormat {
+    // "41 42 43"
+    #[default]
+    Hex,
+    // The bytes themselves, as far as they are valid text
+    Raw,
+    // An array to paste into C code, like 'xxd -i' makes
+    CArray,
+}
+
+impl ClipFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "hex" => Some(ClipFormat::Hex),
+            "raw" => Some(ClipFormat::Raw),
+            "c" => Some(ClipFormat::CArray),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Default, PartialEq, Debug)]
+pub enum Backend {
+    #[default]
+    Osc52,
+    // Program and arguments, the text is written to its standard input
+    Command(Vec<String>),
+}
+
+impl Backend {
+    // Other names than the known ones are run as program
+    pub fn from_name(name: &str) -> Self {
+        let args: &[&str] = match name {
+            "osc52" => return Backend::Osc52,
+            "xclip" => &["xclip", "-selection", "clipboard"],
+            "wl-copy" => &["wl-copy"],
+            program => &[program],
+        };
+        Backend::Command(args.iter().map(|a| a.to_string()).collect())
+    }
+}
+
+pub fn format(data: &[u8], format: ClipFormat) -> String {
+    match format {
+        ClipFormat::Hex => {
+            let hex: Vec<String> = data.iter().map(|b| format!("{:02X}", b)).collect();
+            hex.join(" ")
+        }
+        ClipFormat::Raw => String::from_utf8_lossy(data).into_owned(),
+        ClipFormat::CArray => {
+            let mut text = String::from("unsigned char data[] = {\n");
+            for line in data.chunks(12) {
+                let hex: Vec<String> = line.iter().map(|b| format!("0x{:02x}", b)).collect();
+                text.push_str(&format!("  {},\n", hex.join(", ")));
+            }
+            text.push_str("};\n");
+            text
+        }
+    }
+}
+
+// Bytes of a hex string like "41 42", "0x41, 0x42" or "\x41\x42"
+pub fn parse_hex(text: &str) -> Option<Vec<u8>> {
+    let mut digits = String::new();
+    for token in text.split(|c: char| c.is_whitespace() || c == ',') {
+        for part in token.split("\\x") {
+            let part = part
+                .strip_prefix("0x")
+                .or_else(|| part.strip_prefix("0X"))
+                .unwrap_or(part);
+            if part.len() % 2 != 0 || !part.chars().all(|c| c.is_ascii_hexdigit()) {
+                return None;
+            }
+            digits.push_str(part);
+        }
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).ok())
+        .collect()
+}
+
+fn base64(data: &[u8]) -> String {
+    const DIGITS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in data.chunks(3) {
+        let bits =
+            chunk.iter().fold(0u32, |acc, b| acc << 8 | *b as u32) << (8 * (3 - chunk.len()));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(DIGITS[(bits >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+// The escape sequence asking the terminal to put the text on the clipboard
+pub fn osc52(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", base64(text.as_bytes()))
+}
+
+pub fn copy(text: &str, backend: &Backend) -> io::Result<()> {
+    match backend {
+        Backend::Osc52 => {
+            let mut out = stdout();
+            out.write_all(osc52(text).as_bytes())?;
+            out.flush()
+        }
+        Backend::Command(args) => {
+            let (program, args) = args
+                .split_first()
+                .ok_or_else(|| io::Error::other("No clipboard program"))?;
+            let mut child = Command::new(program)
+                .args(args)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()?;
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin.write_all(text.as_bytes())?;
+            }
+            let status = child.wait()?;
+            if !status.success() {
+                return Err(io::Error::other(format!("{} failed: {}", program, status)));
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./clipboard_test.rs"]
+mod clipboard_test;
@@ -0,0 +1,77 @@
+use super::*;
+
+#[test]
+fn clipboard_format_hex() {
+    assert_eq!(format(&[0x41, 0x0A, 0xFF], ClipFormat::Hex), "41 0A FF");
+}
+#[test]
+fn clipboard_format_raw() {
+    assert_eq!(format(b"hello", ClipFormat::Raw), "hello");
+}
+#[test]
+fn clipboard_format_c_array() {
+    let data: Vec<u8> = (0..13).collect();
+    assert_eq!(
+        format(&data, ClipFormat::CArray),
+        "unsigned char data[] = {\n  \
+         0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b,\n  \
+         0x0c,\n};\n"
+    );
+}
+#[test]
+fn clipboard_base64() {
+    assert_eq!(base64(b""), "");
+    assert_eq!(base64(b"f"), "Zg==");
+    assert_eq!(base64(b"fo"), "Zm8=");
+    assert_eq!(base64(b"foo"), "Zm9v");
+    assert_eq!(base64(b"foob"), "Zm9vYg==");
+}
+#[test]
+fn clipboard_osc52() {
+    assert_eq!(osc52("foo"), "\x1b]52;c;Zm9v\x07");
+}
+#[test]
+fn clipboard_backend_names() {
+    assert_eq!(Backend::from_name("osc52"), Backend::Osc52);
+    assert_eq!(
+        Backend::from_name("wl-copy"),
+        Backend::Command(vec![String::from("wl-copy")])
+    );
+    assert_eq!(
+        Backend::from_name("/usr/local/bin/clip"),
+        Backend::Command(vec![String::from("/usr/local/bin/clip")])
+    );
+}
+#[test]
+fn clipboard_copy_to_command() {
+    let path = std::env::temp_dir().join("hexdino_clipboard_test_copy");
+    let stub = format!("cat > {}", path.display());
+    let backend = Backend::Command(vec![String::from("sh"), String::from("-c"), stub]);
+    copy("41 42", &backend).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "41 42");
+    std::fs::remove_file(&path).unwrap();
+}
+#[test]
+fn clipboard_copy_failing_command() {
+    let backend = Backend::Command(vec![String::from("false")]);
+    assert!(copy("41", &backend).is_err());
+}
+#[test]
+fn clipboard_parse_hex() {
+    assert_eq!(parse_hex("41 42\n43"), Some(vec![0x41, 0x42, 0x43]));
+    assert_eq!(parse_hex("4142ff"), Some(vec![0x41, 0x42, 0xFF]));
+    assert_eq!(parse_hex("0x41, 0X42"), Some(vec![0x41, 0x42]));
+    assert_eq!(parse_hex("\\x41\\x42"), Some(vec![0x41, 0x42]));
+    assert_eq!(parse_hex(" "), Some(vec![]));
+}
+#[test]
+fn clipboard_parse_hex_invalid() {
+    assert_eq!(parse_hex("414"), None);
+    assert_eq!(parse_hex("hello"), None);
+    assert_eq!(parse_hex("0x4g"), None);
+}
+#[test]
+fn clipboard_parse_hex_of_format() {
+    let data = vec![0x00, 0x7F, 0xFF];
+    assert_eq!(parse_hex(&format(&data, ClipFormat::Hex)), Some(data));
+}
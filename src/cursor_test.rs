@@ -1,3 +1,6 @@
+// The tests set up the cursor field by field, which reads best for these cases
+#![allow(clippy::field_reassign_with_default)]
+
 use super::*;
 
 #[test]
@@ -0,0 +1,80 @@
+// Byte by byte comparison of two buffers, as used by the diff mode.
+// Bytes are compared at the same position, bytes only present in the
+// longer buffer count as different. A change is a run of differing bytes.
+// Huge files are compared in chunks, so they never need to be in memory.
+
+use std::cmp;
+use std::ops::Range;
+
+use super::Buffer;
+
+const CHUNK: usize = 1 << 16;
+
+fn len(a: &Buffer, b: &Buffer) -> usize {
+    cmp::max(a.len(), b.len())
+}
+
+// First position at or after from, where the bytes differ (or are equal)
+fn find_forward(a: &Buffer, b: &Buffer, from: usize, differ: bool) -> Option<usize> {
+    let len = len(a, b);
+    let mut start = from;
+    while start < len {
+        let end = cmp::min(start + CHUNK, len);
+        let x = a.read(start..end);
+        let y = b.read(start..end);
+        if let Some(i) = (0..end - start).find(|&i| (x.get(i) != y.get(i)) == differ) {
+            return Some(start + i);
+        }
+        start = end;
+    }
+    None
+}
+
+// Last position before to, where the bytes differ (or are equal)
+fn find_backward(a: &Buffer, b: &Buffer, to: usize, differ: bool) -> Option<usize> {
+    let mut end = cmp::min(to, len(a, b));
+    while end > 0 {
+        let start = end.saturating_sub(CHUNK);
+        let x = a.read(start..end);
+        let y = b.read(start..end);
+        if let Some(i) = (0..end - start).rfind(|&i| (x.get(i) != y.get(i)) == differ) {
+            return Some(start + i);
+        }
+        end = start;
+    }
+    None
+}
+
+pub fn differs(a: &Buffer, b: &Buffer, pos: usize) -> bool {
+    a.get(pos) != b.get(pos)
+}
+
+// The change containing pos, if the bytes at pos differ
+pub fn change_at(a: &Buffer, b: &Buffer, pos: usize) -> Option<Range<usize>> {
+    if !differs(a, b, pos) {
+        return None;
+    }
+    let start = find_backward(a, b, pos, false).map_or(0, |p| p + 1);
+    let end = find_forward(a, b, pos, false).unwrap_or(len(a, b));
+    Some(start..end)
+}
+
+// Start of the next change after the one at pos, like ']c'
+pub fn next_change(a: &Buffer, b: &Buffer, pos: usize) -> Option<usize> {
+    let after = find_forward(a, b, pos, false)?;
+    find_forward(a, b, after, true)
+}
+
+// Start of the change before the one at pos, like '[c'
+pub fn previous_change(a: &Buffer, b: &Buffer, pos: usize) -> Option<usize> {
+    let start = match change_at(a, b, pos) {
+        Some(change) => change.start,
+        None => pos,
+    };
+    let last = find_backward(a, b, start, true)?;
+    Some(find_backward(a, b, last, false).map_or(0, |p| p + 1))
+}
+
+#[cfg(test)]
+#[path = "./diff_test.rs"]
+mod diff_test;
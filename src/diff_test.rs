@@ -0,0 +1,50 @@
+use super::*;
+
+fn buffers() -> (Buffer, Buffer) {
+    let a = Buffer::from(vec![0, 1, 2, 3, 4, 5, 6, 7]);
+    let b = Buffer::from(vec![0, 9, 9, 3, 4, 9, 6, 7, 8, 8]);
+    (a, b)
+}
+
+#[test]
+fn diff_differs() {
+    let (a, b) = buffers();
+    assert!(!differs(&a, &b, 0));
+    assert!(differs(&a, &b, 1));
+    // Only present in one of them
+    assert!(differs(&a, &b, 9));
+    assert!(!differs(&a, &b, 10));
+}
+#[test]
+fn diff_change_at() {
+    let (a, b) = buffers();
+    assert_eq!(change_at(&a, &b, 2), Some(1..3));
+    assert_eq!(change_at(&a, &b, 5), Some(5..6));
+    assert_eq!(change_at(&a, &b, 8), Some(8..10));
+    assert_eq!(change_at(&a, &b, 3), None);
+}
+#[test]
+fn diff_next_change() {
+    let (a, b) = buffers();
+    assert_eq!(next_change(&a, &b, 0), Some(1));
+    assert_eq!(next_change(&a, &b, 1), Some(5));
+    assert_eq!(next_change(&a, &b, 2), Some(5));
+    assert_eq!(next_change(&a, &b, 5), Some(8));
+    assert_eq!(next_change(&a, &b, 8), None);
+}
+#[test]
+fn diff_previous_change() {
+    let (a, b) = buffers();
+    assert_eq!(previous_change(&a, &b, 9), Some(5));
+    assert_eq!(previous_change(&a, &b, 7), Some(5));
+    assert_eq!(previous_change(&a, &b, 5), Some(1));
+    assert_eq!(previous_change(&a, &b, 2), None);
+}
+#[test]
+fn diff_equal_buffers() {
+    let a = Buffer::from(vec![1, 2, 3]);
+    let b = Buffer::from(vec![1, 2, 3]);
+    assert_eq!(next_change(&a, &b, 0), None);
+    assert_eq!(previous_change(&a, &b, 2), None);
+    assert_eq!(change_at(&a, &b, 1), None);
+}
@@ -0,0 +1,54 @@
+// A file being edited, with everything that belongs to it.
+// Every document keeps its own cursor, scroll position and undo history, so switching
+// between documents continues where the user left off.
+
+use anyhow::Error;
+use std::path::PathBuf;
+
+use super::file;
+use super::Buffer;
+use super::Cursor;
+use super::History;
+
+#[derive(Default)]
+pub struct Document {
+    pub buf: Buffer,
+    // None until the data gets a filename, E.g. when started without a file
+    pub path: Option<PathBuf>,
+    // Where the cursor and the screen were when the document was last shown
+    pub cursor: Cursor,
+    // 0 = display data from first line of file
+    pub screenoffset: usize,
+    pub history: History,
+}
+
+impl Document {
+    // A file that does not exist yet results in an empty document
+    pub fn open(path: PathBuf, readonly: bool) -> Result<Self, Error> {
+        Ok(Document {
+            buf: file::load(&path, readonly)?.unwrap_or_default(),
+            path: Some(path),
+            ..Default::default()
+        })
+    }
+    pub fn name(&self) -> String {
+        match &self.path {
+            Some(path) => path.display().to_string(),
+            None => String::from("[No Name]"),
+        }
+    }
+    pub fn is_modified(&self) -> bool {
+        self.history.is_modified()
+    }
+}
+
+// Two different documents at once, E.g. to copy data between them
+pub fn pair(docs: &mut [Document], a: usize, b: usize) -> (&mut Document, &mut Document) {
+    if a < b {
+        let (left, right) = docs.split_at_mut(b);
+        (&mut left[a], &mut right[0])
+    } else {
+        let (left, right) = docs.split_at_mut(a);
+        (&mut right[0], &mut left[b])
+    }
+}
@@ -6,12 +6,17 @@ use crossterm::{
     },
     terminal,
 };
+use std::cmp;
 use std::cmp::Ordering;
 use std::io::prelude::*;
 use std::io::stdout;
+use std::ops::Range;
 
+use super::Buffer;
 use super::Cursor;
 
+use super::Rect;
+
 fn clear_draw() -> Result<(), Error> {
     let mut out = stdout();
     queue!(out, terminal::Clear(terminal::ClearType::All))?;
@@ -20,91 +25,238 @@ fn clear_draw() -> Result<(), Error> {
     Ok(())
 }
 
-pub fn draw(
-    total_buf: &[u8],
-    cols: usize,
-    command: &String,
-    infotext: &String,
-    cursor: Cursor,
-    screenoffset: usize,
-) -> Result<(), Error> {
-    let draw_range = get_absolute_draw_indices(total_buf.len(), cols, screenoffset);
+// A window on the screen, with the data it shows
+pub struct View<'a> {
+    pub buf: &'a Buffer,
+    pub cursor: Cursor,
+    pub screenoffset: usize,
+    pub rect: Rect,
+    pub status: String,
+    pub active: bool,
+    // The data compared with in diff mode, differing bytes are highlighted
+    pub diff: Option<&'a Buffer>,
+    // Bytes selected in visual mode
+    pub selection: Option<Range<usize>>,
+    // Matches of the last search, like hlsearch in vim
+    pub matches: Vec<Range<usize>>,
+}
+
+// The screen without the last line, which is reserved for
+// Status/Commands/etc (Like in vim)
+pub fn screen_area() -> Rect {
+    let screensize = crossterm::terminal::size().unwrap_or_default();
+    Rect {
+        x: 0,
+        y: 0,
+        width: screensize.0 as usize,
+        height: (screensize.1 as usize).saturating_sub(1),
+    }
+}
+
+// Prints within one line of a window, what does not fit is cut off
+struct Line {
+    left: usize,
+}
 
-    let buf = &total_buf[draw_range.0..draw_range.1];
+impl Line {
+    fn print<W: Write>(&mut self, out: &mut W, text: &str) -> Result<(), Error> {
+        let visible: String = text.chars().take(self.left).collect();
+        self.left -= visible.chars().count();
+        queue!(out, Print(visible))?;
+        Ok(())
+    }
+}
 
+pub fn draw(views: &[View], cols: usize, command: &String, infotext: &String) -> Result<(), Error> {
     clear_draw()?;
     let mut out = stdout();
 
     let screensize = crossterm::terminal::size()?;
+    let screenwidth: usize = screensize.0 as usize;
     let screenheight: usize = screensize.1 as usize;
 
-    let buflen_minus_one = buf.len().saturating_sub(1);
-    let rows = buflen_minus_one / cols + 1;
+    let split = views.len() > 1;
+    for view in views {
+        draw_view(view, cols, split)?;
+        if split {
+            draw_status(view)?;
+        }
+        // Separator to the window on the right
+        let x = view.rect.x + view.rect.width;
+        if x < screenwidth {
+            for y in view.rect.y..view.rect.y + view.rect.height {
+                queue!(out, cursor::MoveTo(x as u16, y as u16), Print("|"))?;
+            }
+        }
+    }
+
+    // Put the cursor on last line of terminal
+    queue!(
+        out,
+        cursor::MoveTo(0, screenheight.saturating_sub(1) as u16)
+    )?;
+    queue!(out, Print(command))?;
+    let mut used = command.chars().count();
+    if !infotext.is_empty() {
+        queue!(out, Print(" ("))?;
+        queue!(out, Print(infotext))?;
+        queue!(out, Print(")"))?;
+        used += infotext.chars().count() + 3;
+    }
+    // Without split, the status is aligned to the right, if there is space left
+    if let (false, Some(view)) = (split, views.first()) {
+        let statuswidth = view.status.chars().count();
+        if used + statuswidth < screenwidth {
+            let column = (screenwidth - statuswidth) as u16;
+            queue!(out, cursor::SavePosition)?;
+            queue!(out, cursor::MoveToColumn(column))?;
+            queue!(out, Print(&view.status))?;
+            queue!(out, cursor::RestorePosition)?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+// The status line below a window, the one of the active window is highlighted
+fn draw_status(view: &View) -> Result<(), Error> {
+    let mut out = stdout();
+    let rect = view.rect;
+    if rect.height == 0 {
+        return Ok(());
+    }
+    queue!(
+        out,
+        cursor::MoveTo(rect.x as u16, (rect.y + rect.height - 1) as u16)
+    )?;
+    if view.active {
+        queue!(out, SetAttribute(Attribute::Reverse))?;
+    }
+    let mut line = Line { left: rect.width };
+    line.print(&mut out, &format!("{:<1$}", view.status, rect.width))?;
+    queue!(out, SetAttribute(Attribute::Reset))?;
+    Ok(())
+}
+
+fn draw_view(view: &View, cols: usize, split: bool) -> Result<(), Error> {
+    let total_buf = view.buf;
+    let cursor = view.cursor;
+    let screenoffset = view.screenoffset;
+    let textrows = view.rect.text_rows(split);
+    let draw_range = get_absolute_draw_indices(total_buf.len(), cols, screenoffset, textrows);
+
+    let buf = total_buf.read(draw_range.0..draw_range.1);
+    let other = view
+        .diff
+        .map(|diff| diff.read(draw_range.0..draw_range.0 + textrows * cols));
+    // Also bytes only present in one of the buffers differ
+    let differs = |pos: usize| other.as_ref().is_some_and(|o| o.get(pos) != buf.get(pos));
+    let selected = |pos: usize| {
+        let absolute = pos + cols * screenoffset;
+        view.selection
+            .as_ref()
+            .is_some_and(|s| s.contains(&absolute))
+    };
+    let matched = |pos: usize| {
+        let absolute = pos + cols * screenoffset;
+        view.matches.iter().any(|m| m.contains(&absolute))
+    };
+    let highlight = |pos: usize| {
+        if differs(pos) {
+            color_diff();
+        }
+        if matched(pos) {
+            color_match();
+        }
+        if selected(pos) {
+            color_selection();
+        }
+    };
+
+    let mut out = stdout();
+
+    // In diff mode, lines only the other buffer has are shown as well
+    let shown = cmp::max(buf.len(), other.as_ref().map_or(0, |o| o.len()));
+    let buflen_minus_one = shown.saturating_sub(1);
+    let rows = cmp::min(buflen_minus_one / cols + 1, textrows);
 
     for z in 0..rows {
+        queue!(
+            out,
+            cursor::MoveTo(view.rect.x as u16, (view.rect.y + z) as u16)
+        )?;
+        let mut line = Line {
+            left: view.rect.width,
+        };
         // 8 hex digits (4GB/cols or 0.25GB@cols=COLS)
         let address: String = format!("{:08X}: ", get_absolute_line(cols, screenoffset, z));
-        queue!(out, Print(address))?;
+        line.print(&mut out, &address)?;
         // Additional space between line number and hex
-        queue!(out, Print(" "))?;
+        line.print(&mut out, " ")?;
         for s in 0..cols {
             let pos: usize = z * cols + s;
             match pos.cmp(&buf.len()) {
                 Ordering::Less => {
+                    highlight(pos);
                     if pos + cols * screenoffset == cursor.pos() {
                         color_left_nibble(cursor);
                     }
                     let left_nibble: String = format!("{:01X}", buf[pos] >> 4);
-                    queue!(out, Print(left_nibble))?;
+                    line.print(&mut out, &left_nibble)?;
                     queue!(out, ResetColor)?;
 
+                    highlight(pos);
                     if pos + cols * screenoffset == cursor.pos() {
                         color_right_nibble(cursor);
                     }
                     let right_nibble: String = format!("{:01X}", buf[pos] & 0x0F);
-                    queue!(out, Print(right_nibble))?;
+                    line.print(&mut out, &right_nibble)?;
                     queue!(out, ResetColor)?;
 
-                    queue!(out, Print(" "))?;
+                    line.print(&mut out, " ")?;
                 }
                 Ordering::Equal => {
                     if pos + cols * screenoffset == cursor.pos() {
                         color_left_nibble(cursor);
                     }
-                    queue!(out, Print("-"))?;
+                    line.print(&mut out, "-")?;
                     queue!(out, ResetColor)?;
 
                     if pos + cols * screenoffset == cursor.pos() {
                         color_right_nibble(cursor);
                     }
-                    queue!(out, Print("-"))?;
+                    line.print(&mut out, "-")?;
                     queue!(out, ResetColor)?;
 
-                    queue!(out, Print(" "))?;
+                    line.print(&mut out, " ")?;
                 }
                 Ordering::Greater => {
-                    queue!(out, Print("-- "))?;
+                    highlight(pos);
+                    line.print(&mut out, "--")?;
+                    queue!(out, ResetColor)?;
+                    line.print(&mut out, " ")?;
                 }
             }
         }
         // Additional space between hex and ascii
-        queue!(out, Print(" "))?;
+        line.print(&mut out, " ")?;
         for s in 0..cols {
             let pos: usize = z * cols + s;
+            highlight(pos);
             color_ascii(pos + cols * screenoffset == cursor.pos(), cursor);
             match pos.cmp(&buf.len()) {
                 Ordering::Less => {
                     if let c @ 32..=126 = buf[pos] {
                         let ascii_symbol: String = format!("{}", c as char);
-                        queue!(out, Print(ascii_symbol))?;
+                        line.print(&mut out, &ascii_symbol)?;
                     } else {
                         // Mark non-ascii symbols
-                        queue!(out, Print("."))?;
+                        line.print(&mut out, ".")?;
                     }
                 }
                 Ordering::Equal => {
                     // Pad ascii with spaces
-                    queue!(out, Print(" "))?;
+                    line.print(&mut out, " ")?;
                 }
                 Ordering::Greater => {
                     // No need to fill characters behind buffer size
@@ -112,40 +264,20 @@ pub fn draw(
             }
             queue!(out, ResetColor)?;
         }
-        queue!(out, Print("\n\r"))?;
-    }
-    for _ in 1..screenheight - rows {
-        // Put the cursor on last line of terminal
-        queue!(out, Print("\n"))?;
-    }
-    queue!(out, Print(command))?;
-    if !infotext.is_empty() {
-        queue!(out, Print(" ("))?;
-        queue!(out, Print(infotext))?;
-        queue!(out, Print(")"))?;
     }
-    out.flush()?;
     Ok(())
 }
 
 fn get_absolute_line(cols: usize, screenoffset: usize, z: usize) -> usize {
     z * cols + screenoffset * cols
 }
-fn get_screen_size(cols: usize) -> usize {
-    let screensize = crossterm::terminal::size().unwrap_or_default();
-    let screenheight: usize = screensize.1 as usize;
-
-    let mut ret: usize = 0;
-
-    // Last line reserved for Status/Commands/etc (Like in vim)
-    if screenheight >= 1 {
-        ret = (screenheight - 1) * cols;
-    }
-    ret
-}
-fn get_absolute_draw_indices(buflen: usize, cols: usize, screenoffset: usize) -> (usize, usize) {
-    // Do we need to round() down to 16 when using get_screen_size()?
-    let max_draw_len: usize = std::cmp::min(buflen, get_screen_size(cols));
+fn get_absolute_draw_indices(
+    buflen: usize,
+    cols: usize,
+    screenoffset: usize,
+    rows: usize,
+) -> (usize, usize) {
+    let max_draw_len: usize = std::cmp::min(buflen, rows * cols);
 
     let starting_pos: usize = screenoffset * cols;
     let mut ending_pos: usize = starting_pos + max_draw_len;
@@ -181,6 +313,20 @@ fn color_ascii(condition: bool, cursor: Cursor) {
         }
     }
 }
+fn color_diff() {
+    queue!(stdout(), SetBackgroundColor(Color::DarkRed)).unwrap_or(());
+}
+fn color_match() {
+    queue!(
+        stdout(),
+        SetBackgroundColor(Color::DarkYellow),
+        SetForegroundColor(Color::Black)
+    )
+    .unwrap_or(());
+}
+fn color_selection() {
+    queue!(stdout(), SetBackgroundColor(Color::DarkBlue)).unwrap_or(());
+}
 // This is the actual cursor
 fn color_cursor() {
     queue!(
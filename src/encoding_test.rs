@@ -0,0 +1,37 @@
+use super::*;
+
+#[test]
+fn encoding_utf16() {
+    assert_eq!(
+        Encoding::Utf16Le.encode("Ab"),
+        Some(vec![0x41, 0x00, 0x62, 0x00])
+    );
+    assert_eq!(
+        Encoding::Utf16Be.encode("Ab"),
+        Some(vec![0x00, 0x41, 0x00, 0x62])
+    );
+    // Outside of the basic plane, as surrogate pair
+    assert_eq!(
+        Encoding::Utf16Le.encode("\u{1F995}"),
+        Some(vec![0x3E, 0xD8, 0x95, 0xDD])
+    );
+}
+#[test]
+fn encoding_latin1() {
+    assert_eq!(Encoding::Latin1.encode("aé"), Some(vec![0x61, 0xE9]));
+    assert_eq!(Encoding::Latin1.encode("€"), None);
+}
+#[test]
+fn encoding_ebcdic() {
+    assert_eq!(
+        Encoding::Ebcdic.encode("AZ az 09"),
+        Some(vec![0xC1, 0xE9, 0x40, 0x81, 0xA9, 0x40, 0xF0, 0xF9])
+    );
+    assert_eq!(Encoding::Ebcdic.encode("€"), None);
+}
+#[test]
+fn encoding_names() {
+    assert_eq!(Encoding::from_name("u16le"), Some(Encoding::Utf16Le));
+    assert_eq!(Encoding::from_name("ebcdic"), Some(Encoding::Ebcdic));
+    assert_eq!(Encoding::from_name("utf32"), None);
+}
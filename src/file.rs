@@ -0,0 +1,220 @@
+// Loading and saving of the edited files
+
+use anyhow::{anyhow, Context, Error};
+use std::collections::hash_map::RandomState;
+use std::ffi::OsString;
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::io::prelude::*;
+use std::io::BufWriter;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use super::Buffer;
+use super::Settings;
+
+// Returns None when the file does not exist yet, it gets created when saved
+pub fn load(path: &Path, readonly: bool) -> Result<Option<Buffer>, Error> {
+    if !path.exists() {
+        check_directory(path)?;
+        return Ok(None);
+    }
+    let file = OpenOptions::new()
+        .read(true)
+        .write(!readonly)
+        .open(path)
+        .context("File could not be opened.")?;
+    Ok(Some(Buffer::open(file)?))
+}
+
+// Regular files are written to a temporary file first, which then replaces
+// the file. So a crash or a full disk never leaves a half written file.
+// Devices and fixed size data are written in place, as the size is given.
+pub fn save(path: &Path, buf: &mut Buffer, settings: &Settings) -> Result<(), Error> {
+    check_directory(path)?;
+    let metadata = fs::metadata(path).ok();
+    let regular = metadata.as_ref().is_none_or(|m| m.file_type().is_file());
+    if settings.fixedsize || !regular {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .context("File could not be opened.")?;
+        buf.save(&mut file)?;
+        return Ok(());
+    }
+
+    // Follow symlinks, so the link stays and its target gets replaced
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    replace(&path, buf, 0..buf.len(), settings.backup)?;
+    *buf = Buffer::open(File::open(&path)?)?;
+    Ok(())
+}
+
+// Writes a range of the data to another file, the edited file stays the same.
+// Even when it is the edited file, its old data stays readable, as it gets
+// replaced instead of overwritten.
+pub fn write(path: &Path, buf: &Buffer, range: Range<usize>, append: bool) -> Result<(), Error> {
+    check_directory(path)?;
+    let regular = fs::metadata(path).map_or(true, |m| m.file_type().is_file());
+    if regular && !append {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        return replace(&path, buf, range, false);
+    }
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .append(append)
+        .truncate(!append)
+        .open(path)
+        .context("File could not be opened.")?;
+    let mut out = BufWriter::new(&mut file);
+    buf.write_range_to(&mut out, range)?;
+    out.flush()?;
+    buf.check_read()?;
+    Ok(())
+}
+
+// Writes all data to another file, which from now on is the edited file
+pub fn save_as(path: &Path, buf: &mut Buffer) -> Result<(), Error> {
+    write(path, buf, 0..buf.len(), false)?;
+    *buf = Buffer::open(File::open(path)?)?;
+    Ok(())
+}
+
+// Whether both paths lead to the same file, E.g. "./foo" and "foo"
+pub fn same_file(a: &Path, b: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        if let (Ok(a), Ok(b)) = (fs::metadata(a), fs::metadata(b)) {
+            return a.dev() == b.dev() && a.ino() == b.ino();
+        }
+    }
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+// Writes the range to a temporary file, which then replaces the file at path
+fn replace(path: &Path, buf: &Buffer, range: Range<usize>, keep_backup: bool) -> Result<(), Error> {
+    let metadata = fs::metadata(path).ok();
+    let mut tmp = TempFile::create(path)?;
+    write_synced(buf, range, &mut tmp.file).context("Temporary file could not be written.")?;
+    buf.check_read()?;
+    if let Some(metadata) = metadata {
+        fs::set_permissions(&tmp.path, metadata.permissions())?;
+        // Only root may give away files, so this is allowed to fail
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            let _ =
+                std::os::unix::fs::fchown(&tmp.file, Some(metadata.uid()), Some(metadata.gid()));
+        }
+        if keep_backup {
+            backup(path)?;
+        }
+    }
+    tmp.persist(path)?;
+    // Make sure the rename itself is stored
+    if let Some(dir) = path.parent() {
+        if let Ok(dir) = File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(())
+}
+
+// A temporary file, which gets removed again unless it replaced the file
+struct TempFile {
+    path: PathBuf,
+    file: File,
+    persisted: bool,
+}
+
+impl TempFile {
+    // Named ".file.tmp" next to the file. Something already there, even a
+    // link, is never reused, a random name is tried instead.
+    fn create(target: &Path) -> Result<Self, Error> {
+        let mut path = sibling(target, ".", ".tmp");
+        let mut attempts = 0;
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => {
+                    return Ok(TempFile {
+                        path,
+                        file,
+                        persisted: false,
+                    })
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists && attempts < 100 => {
+                    let random = RandomState::new().build_hasher().finish();
+                    path = sibling(target, ".", &format!(".{:08x}.tmp", random as u32));
+                    attempts += 1;
+                }
+                Err(e) => {
+                    return Err(Error::from(e).context("Temporary file could not be created."))
+                }
+            }
+        }
+    }
+    fn persist(mut self, path: &Path) -> Result<(), Error> {
+        fs::rename(&self.path, path).context("File could not be replaced.")?;
+        self.persisted = true;
+        Ok(())
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if !self.persisted {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+fn write_synced(buf: &Buffer, range: Range<usize>, file: &mut File) -> io::Result<()> {
+    let mut out = BufWriter::new(&mut *file);
+    buf.write_range_to(&mut out, range)?;
+    out.flush()?;
+    drop(out);
+    file.sync_all()
+}
+
+// Keeps the previous content as "file~"
+fn backup(path: &Path) -> Result<(), Error> {
+    let backup_path = sibling(path, "", "~");
+    let _ = fs::remove_file(&backup_path);
+    // A hard link is cheap and keeps the old data, as the file gets replaced
+    if fs::hard_link(path, &backup_path).is_err() {
+        fs::copy(path, &backup_path).context("Backup file could not be written.")?;
+    }
+    Ok(())
+}
+
+// A file next to path, with the name surrounded by prefix and suffix
+fn sibling(path: &Path, prefix: &str, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(prefix);
+    name.push(path.file_name().unwrap_or_default());
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+fn check_directory(path: &Path) -> Result<(), Error> {
+    match path.parent() {
+        // A plain filename is relative to the current directory
+        Some(dir) if !dir.as_os_str().is_empty() && !dir.is_dir() => {
+            Err(anyhow!("Directory does not exist: {}", dir.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+#[path = "./file_test.rs"]
+mod file_test;
@@ -0,0 +1,201 @@
+use super::*;
+
+#[test]
+fn file_load_missing_file() {
+    let path = std::env::temp_dir().join("hexdino_file_test_missing_file");
+    assert!(load(&path, false).unwrap().is_none());
+    assert!(!path.exists());
+}
+#[test]
+fn file_load_missing_directory() {
+    let path = std::env::temp_dir().join("hexdino_file_test_missing_dir/file");
+    assert!(load(&path, false).is_err());
+}
+#[test]
+fn file_save_creates_file() {
+    let path = std::env::temp_dir().join("hexdino_file_test_save_creates_file");
+    let mut buf = Buffer::from(vec![0x01, 0x02]);
+    save(&path, &mut buf, &Settings::default()).unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), vec![0x01, 0x02]);
+    std::fs::remove_file(&path).unwrap();
+}
+#[test]
+fn file_save_replaces_file() {
+    let path = std::env::temp_dir().join("hexdino_file_test_save_replaces_file");
+    std::fs::write(&path, [0x01, 0x02, 0x03]).unwrap();
+    let mut buf = load(&path, false).unwrap().unwrap();
+    buf.remove(0..1);
+    save(&path, &mut buf, &Settings::default()).unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), vec![0x02, 0x03]);
+    assert!(!sibling(&path, ".", ".tmp").exists());
+    std::fs::remove_file(&path).unwrap();
+}
+#[cfg(unix)]
+#[test]
+fn file_save_keeps_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+    let path = std::env::temp_dir().join("hexdino_file_test_save_keeps_permissions");
+    std::fs::write(&path, [0x01, 0x02, 0x03]).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o640)).unwrap();
+    let mut buf = load(&path, false).unwrap().unwrap();
+    buf.insert(0, &[0x00]);
+    save(&path, &mut buf, &Settings::default()).unwrap();
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o640);
+    std::fs::remove_file(&path).unwrap();
+}
+#[test]
+fn file_save_with_backup() {
+    let path = std::env::temp_dir().join("hexdino_file_test_save_with_backup");
+    std::fs::write(&path, [0x01, 0x02, 0x03]).unwrap();
+    let mut buf = load(&path, false).unwrap().unwrap();
+    buf.remove(0..1);
+    let settings = Settings {
+        backup: true,
+        ..Default::default()
+    };
+    save(&path, &mut buf, &settings).unwrap();
+    let backup_path = sibling(&path, "", "~");
+    assert_eq!(std::fs::read(&backup_path).unwrap(), vec![0x01, 0x02, 0x03]);
+    assert_eq!(std::fs::read(&path).unwrap(), vec![0x02, 0x03]);
+    std::fs::remove_file(&path).unwrap();
+    std::fs::remove_file(&backup_path).unwrap();
+}
+#[test]
+fn file_save_fixedsize_in_place() {
+    let path = std::env::temp_dir().join("hexdino_file_test_save_fixedsize_in_place");
+    std::fs::write(&path, [0x01, 0x02, 0x03]).unwrap();
+    let mut buf = load(&path, false).unwrap().unwrap();
+    buf.splice(1..2, &[0xFF]);
+    let settings = Settings {
+        fixedsize: true,
+        ..Default::default()
+    };
+    save(&path, &mut buf, &settings).unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), vec![0x01, 0xFF, 0x03]);
+    std::fs::remove_file(&path).unwrap();
+}
+#[test]
+fn file_write_range() {
+    let path = std::env::temp_dir().join("hexdino_file_test_write_range");
+    let buf = Buffer::from(vec![0x01, 0x02, 0x03, 0x04]);
+    write(&path, &buf, 1..3, false).unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), vec![0x02, 0x03]);
+    std::fs::remove_file(&path).unwrap();
+}
+#[test]
+fn file_write_append() {
+    let path = std::env::temp_dir().join("hexdino_file_test_write_append");
+    std::fs::write(&path, [0xAA]).unwrap();
+    let buf = Buffer::from(vec![0x01, 0x02]);
+    write(&path, &buf, 0..2, true).unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), vec![0xAA, 0x01, 0x02]);
+    std::fs::remove_file(&path).unwrap();
+}
+#[test]
+fn file_save_as_switches_file() {
+    let path = std::env::temp_dir().join("hexdino_file_test_save_as_switches_file");
+    let mut buf = Buffer::from(vec![0x01, 0x02, 0x03]);
+    save_as(&path, &mut buf).unwrap();
+    // The buffer is now based on the new file, so in place saving works
+    buf.splice(0..1, &[0xFF]);
+    let settings = Settings {
+        fixedsize: true,
+        ..Default::default()
+    };
+    save(&path, &mut buf, &settings).unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), vec![0xFF, 0x02, 0x03]);
+    std::fs::remove_file(&path).unwrap();
+}
+#[test]
+fn file_save_refuses_unreadable_data() {
+    let path = std::env::temp_dir().join("hexdino_file_test_save_refuses_unreadable_data");
+    std::fs::write(&path, [0x01, 0x02, 0x03]).unwrap();
+    let mut buf = load(&path, false).unwrap().unwrap();
+    buf.remove(0..1);
+    // Truncated from outside before the data was read
+    std::fs::write(&path, [0x01]).unwrap();
+    assert!(save(&path, &mut buf, &Settings::default()).is_err());
+    assert_eq!(std::fs::read(&path).unwrap(), vec![0x01]);
+    assert!(!sibling(&path, ".", ".tmp").exists());
+    std::fs::remove_file(&path).unwrap();
+}
+#[test]
+fn file_save_failing_removes_temporary_file() {
+    let path = std::env::temp_dir().join("hexdino_file_test_save_failing_removes_tmp");
+    std::fs::write(&path, [0x01, 0x02, 0x03]).unwrap();
+    let mut buf = load(&path, false).unwrap().unwrap();
+    buf.remove(0..1);
+    // A directory in place of the backup makes the save fail
+    let backup_path = sibling(&path, "", "~");
+    std::fs::create_dir_all(&backup_path).unwrap();
+    let settings = Settings {
+        backup: true,
+        ..Default::default()
+    };
+    assert!(save(&path, &mut buf, &settings).is_err());
+    assert!(!sibling(&path, ".", ".tmp").exists());
+    assert_eq!(std::fs::read(&path).unwrap(), vec![0x01, 0x02, 0x03]);
+    std::fs::remove_file(&path).unwrap();
+    std::fs::remove_dir(&backup_path).unwrap();
+}
+#[test]
+fn file_write_over_edited_file() {
+    let path = std::env::temp_dir().join("hexdino_file_test_write_over_edited_file");
+    let data: Vec<u8> = (0..300_000).map(|i| (i % 251) as u8).collect();
+    std::fs::write(&path, &data).unwrap();
+    let buf = load(&path, false).unwrap().unwrap();
+    // Only the first page is loaded, the rest is read while writing
+    assert_eq!(buf.get(0), Some(0));
+    write(&path, &buf, 0..buf.len(), false).unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), data);
+    let mut buf = load(&path, false).unwrap().unwrap();
+    save_as(&path, &mut buf).unwrap();
+    assert_eq!(buf.to_vec(), data);
+    std::fs::remove_file(&path).unwrap();
+}
+#[test]
+fn file_same_file() {
+    let path = std::env::temp_dir().join("hexdino_file_test_same_file");
+    std::fs::write(&path, [0x01]).unwrap();
+    let other = std::env::temp_dir()
+        .join(".")
+        .join("hexdino_file_test_same_file");
+    assert!(same_file(&path, &other));
+    assert!(!same_file(&path, &sibling(&path, "", "~")));
+    std::fs::remove_file(&path).unwrap();
+}
+#[test]
+fn file_save_keeps_existing_temporary_file() {
+    let path = std::env::temp_dir().join("hexdino_file_test_save_keeps_existing_tmp");
+    let tmp_path = sibling(&path, ".", ".tmp");
+    std::fs::write(&path, [0x01, 0x02, 0x03]).unwrap();
+    std::fs::write(&tmp_path, [0xAA]).unwrap();
+    let mut buf = load(&path, false).unwrap().unwrap();
+    buf.remove(0..1);
+    save(&path, &mut buf, &Settings::default()).unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), vec![0x02, 0x03]);
+    assert_eq!(std::fs::read(&tmp_path).unwrap(), vec![0xAA]);
+    std::fs::remove_file(&path).unwrap();
+    std::fs::remove_file(&tmp_path).unwrap();
+}
+#[cfg(unix)]
+#[test]
+fn file_save_ignores_link_at_temporary_file() {
+    let path = std::env::temp_dir().join("hexdino_file_test_save_ignores_link_at_tmp");
+    let tmp_path = sibling(&path, ".", ".tmp");
+    let victim = sibling(&path, "", ".victim");
+    std::fs::write(&path, [0x01, 0x02, 0x03]).unwrap();
+    std::fs::write(&victim, [0xAA]).unwrap();
+    let _ = std::fs::remove_file(&tmp_path);
+    std::os::unix::fs::symlink(&victim, &tmp_path).unwrap();
+    let mut buf = load(&path, false).unwrap().unwrap();
+    buf.remove(0..1);
+    save(&path, &mut buf, &Settings::default()).unwrap();
+    assert_eq!(std::fs::read(&victim).unwrap(), vec![0xAA]);
+    assert!(!std::fs::symlink_metadata(&path).unwrap().is_symlink());
+    assert_eq!(std::fs::read(&path).unwrap(), vec![0x02, 0x03]);
+    std::fs::remove_file(&path).unwrap();
+    std::fs::remove_file(&tmp_path).unwrap();
+    std::fs::remove_file(&victim).unwrap();
+}
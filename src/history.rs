@@ -0,0 +1,134 @@
+// Journal of all edits done to the data, used for undo and redo.
+// Every command (or every insert sequence from 'i' until escape) forms one
+// step. A step remembers the cursor from before and after the command, so
+// undo and redo can also restore where the user was.
+
+use std::ops::Range;
+
+use super::Buffer;
+use super::Cursor;
+
+// At pos, the bytes in old got replaced by the bytes in new
+struct Change {
+    pos: usize,
+    old: Vec<u8>,
+    new: Vec<u8>,
+}
+
+struct Step {
+    id: usize,
+    changes: Vec<Change>,
+    cursor_before: Cursor,
+    cursor_after: Cursor,
+}
+
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Step>,
+    redo: Vec<Step>,
+    // The step which is still being recorded
+    current: Option<Step>,
+    // Cursor when the current command started
+    cursor_before: Cursor,
+    // Every step gets its own id, 0 is the unmodified data
+    last_id: usize,
+    // Id of the step which was last written to the file
+    saved: usize,
+}
+
+impl History {
+    // Call before a command is processed. The cursor is only remembered when
+    // no step is open, so an insert sequence keeps its initial cursor.
+    pub fn prepare(&mut self, cursor: Cursor) {
+        if self.current.is_none() {
+            self.cursor_before = cursor;
+        }
+    }
+    // Call when a command is complete, this finishes the current step
+    pub fn close(&mut self, cursor: Cursor) {
+        if let Some(mut step) = self.current.take() {
+            step.cursor_after = cursor;
+            self.undo.push(step);
+        }
+        self.cursor_before = cursor;
+    }
+    // The edits return the bytes that got replaced or removed
+    pub fn splice(&mut self, buf: &mut Buffer, range: Range<usize>, data: &[u8]) -> Vec<u8> {
+        let old = buf.splice(range.clone(), data);
+        if old == data {
+            return old;
+        }
+        let cursor_before = self.cursor_before;
+        if self.current.is_none() {
+            self.last_id += 1;
+        }
+        let id = self.last_id;
+        let step = self.current.get_or_insert_with(|| Step {
+            id,
+            changes: Vec::new(),
+            cursor_before,
+            cursor_after: cursor_before,
+        });
+        step.changes.push(Change {
+            pos: range.start,
+            old: old.clone(),
+            new: data.to_vec(),
+        });
+        // A new change makes the undone steps unreachable
+        self.redo.clear();
+        old
+    }
+    pub fn insert(&mut self, buf: &mut Buffer, pos: usize, data: &[u8]) {
+        self.splice(buf, pos..pos, data);
+    }
+    pub fn remove(&mut self, buf: &mut Buffer, range: Range<usize>) -> Vec<u8> {
+        self.splice(buf, range, &[])
+    }
+    pub fn replace(&mut self, buf: &mut Buffer, pos: usize, data: &[u8]) -> Vec<u8> {
+        self.splice(buf, pos..pos + data.len(), data)
+    }
+    // Id of the step the data currently corresponds to
+    fn state(&self) -> usize {
+        match (&self.current, self.undo.last()) {
+            (Some(step), _) | (None, Some(step)) => step.id,
+            (None, None) => 0,
+        }
+    }
+    // Call after the data got written to the file
+    pub fn mark_saved(&mut self) {
+        self.saved = self.state();
+    }
+    // Data is not modified when it is the same as when it was last saved,
+    // this is also true after undoing back to that point
+    pub fn is_modified(&self) -> bool {
+        self.state() != self.saved
+    }
+    // Returns the cursor to restore, or None if there is nothing to undo
+    pub fn undo(&mut self, buf: &mut Buffer) -> Option<Cursor> {
+        let step = self.undo.pop()?;
+        for change in step.changes.iter().rev() {
+            let range = change.pos..change.pos + change.new.len();
+            buf.splice(range, &change.old);
+        }
+        let cursor = step.cursor_before;
+        self.redo.push(step);
+        self.cursor_before = cursor;
+        Some(cursor)
+    }
+    // Returns the cursor to restore, or None if there is nothing to redo
+    pub fn redo(&mut self, buf: &mut Buffer) -> Option<Cursor> {
+        let step = self.redo.pop()?;
+        for change in step.changes.iter() {
+            let range = change.pos..change.pos + change.old.len();
+            buf.splice(range, &change.new);
+        }
+        let cursor = step.cursor_after;
+        self.undo.push(step);
+        self.cursor_before = cursor;
+        Some(cursor)
+    }
+}
+
+#[cfg(test)]
+#[path = "./history_test.rs"]
+mod history_test;
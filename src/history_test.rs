@@ -0,0 +1,143 @@
+use super::*;
+use crate::CursorSelects;
+
+#[test]
+fn history_undo_remove() {
+    let mut buf = Buffer::from((0..5).collect::<Vec<u8>>());
+    let mut history = History::default();
+    history.prepare(Cursor::default());
+    history.remove(&mut buf, 1..3);
+    history.close(Cursor::default());
+    assert_eq!(buf.to_vec(), vec![0, 3, 4]);
+    history.undo(&mut buf);
+    assert_eq!(buf.to_vec(), vec![0, 1, 2, 3, 4]);
+}
+#[test]
+fn history_redo_remove() {
+    let mut buf = Buffer::from((0..5).collect::<Vec<u8>>());
+    let mut history = History::default();
+    history.prepare(Cursor::default());
+    history.remove(&mut buf, 1..3);
+    history.close(Cursor::default());
+    history.undo(&mut buf);
+    history.redo(&mut buf);
+    assert_eq!(buf.to_vec(), vec![0, 3, 4]);
+}
+#[test]
+fn history_undo_without_changes() {
+    let mut buf = Buffer::from((0..5).collect::<Vec<u8>>());
+    let mut history = History::default();
+    assert!(history.undo(&mut buf).is_none());
+    assert!(history.redo(&mut buf).is_none());
+    assert_eq!(buf.to_vec(), vec![0, 1, 2, 3, 4]);
+}
+#[test]
+fn history_undo_groups_changes_of_one_step() {
+    let mut buf = Buffer::from((0..3).collect::<Vec<u8>>());
+    let mut history = History::default();
+    history.prepare(Cursor::default());
+    history.insert(&mut buf, 0, &[0xAA]);
+    history.insert(&mut buf, 1, &[0xBB]);
+    history.replace(&mut buf, 2, &[0xCC]);
+    history.close(Cursor::default());
+    assert_eq!(buf.to_vec(), vec![0xAA, 0xBB, 0xCC, 1, 2]);
+    history.undo(&mut buf);
+    assert_eq!(buf.to_vec(), vec![0, 1, 2]);
+    assert!(history.undo(&mut buf).is_none());
+}
+#[test]
+fn history_undo_restores_cursor() {
+    let mut buf = Buffer::from((0..5).collect::<Vec<u8>>());
+    let mut history = History::default();
+    let mut cursor = Cursor::default();
+    cursor.set_pos(3);
+    cursor.select_ascii();
+    history.prepare(cursor);
+    cursor.set_pos(1);
+    cursor.select_right_nibble();
+    history.remove(&mut buf, 1..2);
+    history.close(cursor);
+    let restored = history.undo(&mut buf).unwrap();
+    assert_eq!(restored.pos(), 3);
+    assert_eq!(restored.selects(), CursorSelects::AsciiChar);
+    let restored = history.redo(&mut buf).unwrap();
+    assert_eq!(restored.pos(), 1);
+    assert_eq!(restored.selects(), CursorSelects::RightNibble);
+}
+#[test]
+fn history_new_change_drops_redo() {
+    let mut buf = Buffer::from((0..5).collect::<Vec<u8>>());
+    let mut history = History::default();
+    history.prepare(Cursor::default());
+    history.remove(&mut buf, 0..1);
+    history.close(Cursor::default());
+    history.undo(&mut buf);
+    history.prepare(Cursor::default());
+    history.remove(&mut buf, 4..5);
+    history.close(Cursor::default());
+    assert!(history.redo(&mut buf).is_none());
+    assert_eq!(buf.to_vec(), vec![0, 1, 2, 3]);
+}
+#[test]
+fn history_unchanged_data_is_no_step() {
+    let mut buf = Buffer::from((0..5).collect::<Vec<u8>>());
+    let mut history = History::default();
+    history.prepare(Cursor::default());
+    history.replace(&mut buf, 2, &[2]);
+    history.close(Cursor::default());
+    assert!(history.undo(&mut buf).is_none());
+}
+#[test]
+fn history_modified_after_change() {
+    let mut buf = Buffer::from((0..5).collect::<Vec<u8>>());
+    let mut history = History::default();
+    assert!(!history.is_modified());
+    history.prepare(Cursor::default());
+    history.remove(&mut buf, 0..1);
+    assert!(history.is_modified());
+    history.close(Cursor::default());
+    assert!(history.is_modified());
+    history.mark_saved();
+    assert!(!history.is_modified());
+}
+#[test]
+fn history_undo_to_saved_is_unmodified() {
+    let mut buf = Buffer::from((0..5).collect::<Vec<u8>>());
+    let mut history = History::default();
+    history.prepare(Cursor::default());
+    history.remove(&mut buf, 0..1);
+    history.close(Cursor::default());
+    history.mark_saved();
+    history.prepare(Cursor::default());
+    history.remove(&mut buf, 0..1);
+    history.close(Cursor::default());
+    assert!(history.is_modified());
+    history.undo(&mut buf);
+    assert!(!history.is_modified());
+    history.undo(&mut buf);
+    assert!(history.is_modified());
+    history.redo(&mut buf);
+    assert!(!history.is_modified());
+}
+#[test]
+fn history_unreachable_saved_state_is_modified() {
+    let mut buf = Buffer::from((0..5).collect::<Vec<u8>>());
+    let mut history = History::default();
+    history.prepare(Cursor::default());
+    history.remove(&mut buf, 0..1);
+    history.close(Cursor::default());
+    history.mark_saved();
+    history.undo(&mut buf);
+    history.prepare(Cursor::default());
+    history.remove(&mut buf, 0..1);
+    history.close(Cursor::default());
+    assert!(history.is_modified());
+}
+#[test]
+fn history_edits_return_old_bytes() {
+    let mut buf = Buffer::from(vec![0x01, 0x02, 0x03, 0x04]);
+    let mut history = History::default();
+    assert_eq!(history.remove(&mut buf, 1..3), vec![0x02, 0x03]);
+    assert_eq!(history.replace(&mut buf, 0, &[0xAA]), vec![0x01]);
+    assert_eq!(buf.to_vec(), vec![0xAA, 0x04]);
+}
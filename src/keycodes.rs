@@ -1,10 +1,16 @@
-use crossterm::event::KeyCode;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
 // We do not care what platform we are on, we convert the platform independent events
 // (E.g. the Enter, Esc and Backspace) to simple characters.
 // We later need these simple fix values when using the pest parser for further processing.
-pub fn extract(input: KeyCode) -> Option<char> {
-    match input {
+pub fn extract(input: KeyEvent) -> Option<char> {
+    match input.code {
+        // Control combinations become the ascii control characters (E.g. Ctrl-R is 0x12)
+        KeyCode::Char(z)
+            if input.modifiers.contains(KeyModifiers::CONTROL) && z.is_ascii_alphabetic() =>
+        {
+            Some(((z.to_ascii_uppercase() as u8) & 0x1F) as char)
+        }
         // This is the most important one:
         KeyCode::Char(z) => Some(z), // Actually extract the character!
 
@@ -3,25 +3,44 @@
 //! A hex editor with vim like keybindings written in Rust.
 #![doc(html_logo_url = "https://raw.githubusercontent.com/Luz/hexdino/master/logo.png")]
 
-use anyhow::{Context, Error};
+use anyhow::{anyhow, Error};
 use clap::Parser as ArgParser;
 use std::cmp;
-use std::io::prelude::*;
-use std::io::SeekFrom;
-use std::path::{Path, PathBuf};
+use std::ops::Range;
+use std::path::PathBuf;
+
+mod buffer;
+use buffer::Buffer;
 
 mod draw;
-use draw::draw;
+use draw::{draw, screen_area, View};
+
+mod diff;
+
+mod clipboard;
+
+mod file;
+
+mod pages;
+use pages::Pages;
+
+mod encoding;
+use encoding::Encoding;
 
 mod search;
 use search::*;
 
-use memmem::{Searcher, TwoWaySearcher};
+mod substitute;
+use substitute::{Replacement, Substitute};
+
+mod value;
 
-use crossterm::event::{read, Event};
+use crossterm::event::{read, DisableBracketedPaste, EnableBracketedPaste, Event};
+use crossterm::execute;
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
 
 mod keycodes;
+use pest::iterators::Pair;
 use pest::Parser;
 use pest_derive::*;
 #[derive(Parser)]
@@ -31,277 +50,723 @@ struct CmdParser;
 mod cursor;
 use cursor::*;
 
+mod history;
+use history::History;
+
+mod document;
+use document::Document;
+
+mod visual;
+use visual::Visual;
+
+mod registers;
+use registers::Registers;
+
+mod window;
+use window::*;
+
+mod settings;
+use settings::{Columns, Settings};
+
 #[derive(ArgParser)]
 #[clap(version, long_about = None)]
 struct Args {
-    // This is always required for now, as we have no commands to load a file
-    #[clap(required = true, value_parser)]
-    filename: PathBuf,
+    /// Without a file, an empty buffer is edited
+    #[clap(value_parser)]
+    filenames: Vec<PathBuf>,
     /// Load commands via argument (E.g.: --autoparse=$'jjxx:q\r' )
     #[clap(short, long, default_value = "")]
     autoparse: String,
+    /// Never change the file size, deletes overwrite with zeros instead
+    #[clap(long)]
+    fixedsize: bool,
+    /// Open the file read-only, to only view it
+    #[clap(short = 'R', long)]
+    view: bool,
+    /// Compare the first two files side by side
+    #[clap(short, long)]
+    diff: bool,
+    /// Bytes per row, or "auto" to fit the terminal width
+    #[clap(long, default_value = "16")]
+    cols: String,
+}
+
+// Removes the range, or overwrites it with zeros when the size is fixed.
+// Returns the removed bytes.
+fn delete(
+    buf: &mut Buffer,
+    history: &mut History,
+    range: Range<usize>,
+    fixedsize: bool,
+) -> Vec<u8> {
+    if fixedsize {
+        let zeros = vec![0; range.len()];
+        history.replace(buf, range.start, &zeros)
+    } else {
+        history.remove(buf, range)
+    }
+}
+
+// Replaces every byte of the range by what convert makes of it
+fn convert<F>(buf: &mut Buffer, history: &mut History, range: Range<usize>, convert: F)
+where
+    F: Fn(u8) -> u8,
+{
+    let data: Vec<u8> = buf.read(range.clone()).into_iter().map(convert).collect();
+    history.replace(buf, range.start, &data);
+}
+
+// Finds a token anywhere within the command, E.g. the filename in ':w foo'
+fn find_token<'a>(cmd: &Pair<'a, Rule>, rule: Rule) -> Option<Pair<'a, Rule>> {
+    cmd.clone()
+        .into_inner()
+        .flatten()
+        .find(|p| p.as_rule() == rule)
+}
+
+// Commands like ':w!' override protections
+fn is_forced(cmd: &Pair<Rule>) -> bool {
+    find_token(cmd, Rule::force).is_some()
+}
+
+fn get_filename(cmd: &Pair<Rule>) -> Option<PathBuf> {
+    find_token(cmd, Rule::filename).map(|p| PathBuf::from(p.as_str().trim()))
+}
+
+// The bytes of the lines in commands like ':10,20w foo', without lines all bytes
+fn get_range(cmd: &Pair<Rule>, cols: usize, len: usize) -> Range<usize> {
+    let lines: Vec<usize> = match find_token(cmd, Rule::range) {
+        Some(range) => range
+            .into_inner()
+            // Line numbers too big for a number are past the end anyway
+            .map(|p| p.as_str().parse().unwrap_or(usize::MAX))
+            .collect(),
+        None => Vec::new(),
+    };
+    match (lines.first(), lines.last()) {
+        (Some(first), Some(last)) => {
+            let start = cmp::min(first.saturating_mul(cols), len);
+            let end = cmp::min(last.saturating_add(1).saturating_mul(cols), len);
+            start..cmp::max(start, end)
+        }
+        _ => 0..len,
+    }
+}
+
+// Position of the amount'th match from pos on, or pos if there is none
+fn find_match(
+    buf: &Buffer,
+    mut pos: usize,
+    search: &LastSearch,
+    backward: bool,
+    amount: usize,
+    infotext: &mut String,
+) -> usize {
+    for _ in 0..amount {
+        match search.pattern.find_next(buf, pos, backward) {
+            Some((found, wrapped)) => {
+                if wrapped {
+                    infotext.clear();
+                    infotext.push_str(match backward {
+                        true => "search hit TOP, continuing at BOTTOM",
+                        false => "search hit BOTTOM, continuing at TOP",
+                    });
+                }
+                pos = found;
+            }
+            None => {
+                infotext.push_str(&format!("Pattern not found: {}", search.pattern.text()));
+                break;
+            }
+        }
+    }
+    pos
+}
+
+// Filename and whether it has unsaved changes, with more
+// documents also which of them is edited
+fn get_status(docs: &[Document], current: usize) -> String {
+    let mut status = String::new();
+    if docs.len() > 1 {
+        status.push_str(&format!("[{}/{}] ", current + 1, docs.len()));
+    }
+    status.push_str(&docs[current].name());
+    if docs[current].is_modified() {
+        status.push_str(" [+]");
+    }
+    status
+}
+
+// Opens the file as additional document, or finds it if it is already open
+fn open_document(
+    docs: &mut Vec<Document>,
+    name: PathBuf,
+    readonly: bool,
+    infotext: &mut String,
+) -> Result<usize, Error> {
+    let open = |d: &Document| d.path.as_ref().is_some_and(|p| file::same_file(p, &name));
+    if let Some(index) = docs.iter().position(open) {
+        return Ok(index);
+    }
+    if !name.exists() {
+        infotext.push_str("[New File]");
+    }
+    docs.push(Document::open(name, readonly)?);
+    Ok(docs.len() - 1)
+}
+
+// Bytes per row, in auto mode the narrowest window decides
+fn get_cols(settings: &Settings, layout: &Layout) -> usize {
+    let rects = layout.rects(screen_area());
+    let width = rects.iter().map(|r| r.width).min().unwrap_or_default();
+    settings.cols.count(width)
+}
+
+// Follows changes of the width and keeps the cursor of the active window on screen
+fn fit_windows(
+    docs: &mut [Document],
+    windows: &mut [Window],
+    layout: &Layout,
+    active: usize,
+    settings: &Settings,
+    cols: &mut usize,
+) {
+    // The width changes with ':set cols', or with the terminal in auto mode,
+    // the windows keep showing about the same data
+    let newcols = get_cols(settings, layout);
+    if newcols != *cols {
+        for win in windows.iter_mut() {
+            win.screenoffset = win.screenoffset * *cols / newcols;
+        }
+        for doc in docs.iter_mut() {
+            doc.screenoffset = doc.screenoffset * *cols / newcols;
+        }
+        *cols = newcols;
+    }
+
+    // Always move screen when cursor leaves screen
+    let rows = layout.rects(screen_area())[active].text_rows(windows.len() > 1);
+    windows[active].scroll_to_cursor(rows, *cols);
+    // Windows in diff mode scroll together
+    if let Some(p) = diff_partner(windows, active) {
+        windows[p].screenoffset = windows[active].screenoffset;
+    }
+}
+
+// What the windows show, only the active window shows the visual selection
+fn get_views<'a>(
+    docs: &'a [Document],
+    windows: &[Window],
+    layout: &Layout,
+    active: usize,
+    visual: Option<Visual>,
+    search: Option<&Pattern>,
+    cols: usize,
+) -> Vec<View<'a>> {
+    let rects = layout.rects(screen_area());
+    let split = windows.len() > 1;
+    windows
+        .iter()
+        .zip(rects)
+        .enumerate()
+        .map(|(i, (win, rect))| View {
+            buf: &docs[win.doc].buf,
+            cursor: win.cursor,
+            screenoffset: win.screenoffset,
+            rect,
+            status: get_status(docs, win.doc),
+            active: i == active,
+            diff: diff_partner(windows, i).map(|p| &docs[windows[p].doc].buf),
+            selection: visual
+                .filter(|_| i == active)
+                .map(|v| v.range(win.cursor.pos(), cols, docs[win.doc].buf.len())),
+            matches: search.map_or(Vec::new(), |pattern| {
+                let start = win.screenoffset * cols;
+                let end = start + rect.text_rows(split) * cols;
+                pattern.matches_in(&docs[win.doc].buf, start..end)
+            }),
+        })
+        .collect()
 }
 
 fn main() -> Result<(), Error> {
     let args = Args::parse();
 
-    let mut buf = Vec::new();
-    let mut cursor = Cursor::default();
-    // 0 = display data from first line of file
-    let mut screenoffset: usize = 0;
-    const COLS: usize = 16;
+    let mut settings = Settings {
+        fixedsize: args.fixedsize,
+        readonly: args.view,
+        cols: Columns::from_name(&args.cols)
+            .ok_or_else(|| anyhow!("Invalid argument: --cols={}", args.cols))?,
+        ..Default::default()
+    };
+    if args.diff && args.filenames.len() < 2 {
+        return Err(anyhow!("-d needs two files"));
+    }
     let mut command = String::new();
     let mut lastcommand = String::new();
+    let mut lastsearch: Option<LastSearch> = None;
+    // Whether the matches of the last search are highlighted
+    let mut hlsearch = false;
+    // A substitution asking for confirmation of every match
+    let mut substitute: Option<Substitute> = None;
     let mut autoparse = args.autoparse;
     let mut infotext = String::new();
 
-    let screensize = crossterm::terminal::size()?;
-    let screenheight: usize = screensize.1 as usize;
-
-    let path = Path::new(&args.filename);
-
-    let mut file = std::fs::OpenOptions::new()
-        .read(true)
-        .write(true)
-        .create(true)
-        .truncate(false)
-        .open(path)
-        .context("File could not be opened.")?;
+    let mut docs = Vec::new();
+    for path in args.filenames {
+        if !path.exists() && docs.is_empty() {
+            infotext.push_str("[New File]");
+        }
+        docs.push(Document::open(path, settings.readonly)?);
+    }
+    if docs.is_empty() {
+        docs.push(Document::default());
+    }
+    let mut windows = vec![Window::default()];
+    let mut layout = Layout::default();
+    // Index of the window being edited
+    let mut active: usize = 0;
+    if args.diff {
+        // The new window of the split is left, it shows the first file
+        windows = vec![
+            Window {
+                doc: 1,
+                diff: true,
+                ..Default::default()
+            },
+            Window {
+                doc: 0,
+                diff: true,
+                ..Default::default()
+            },
+        ];
+        layout.split(0, 1, Split::Vertical);
+        active = 1;
+    }
 
-    file.read_to_end(&mut buf)?;
+    let mut cols = get_cols(&settings, &layout);
 
     enable_raw_mode()?;
-    draw(&buf, COLS, &command, &infotext, cursor, screenoffset)?;
+    // Pasted text arrives as a whole, instead of as single keys
+    execute!(std::io::stdout(), EnableBracketedPaste)?;
+    let views = get_views(&docs, &windows, &layout, active, None, None, cols);
+    draw(&views, cols, &command, &infotext)?;
 
+    let mut visual: Option<Visual> = None;
+    let mut registers = Registers::default();
+    // Register given by '"x' for the next command
+    let mut register: Option<char> = None;
     let mut quitnow = false;
     while !quitnow {
+        let mut pasted: Option<String> = None;
         if autoparse.is_empty() {
             let key = read()?;
             let mut keycode: char = '\u{00}';
             // This is close to the old c-style 'getch()':
             match key {
                 Event::Key(event) => {
-                    keycode = keycodes::extract(event.code).unwrap_or('\u{00}');
+                    keycode = keycodes::extract(event).unwrap_or('\u{00}');
                 }
                 Event::Mouse(_event) => (), // This can be handled later
                 Event::FocusGained => (),   // This can be handled later
                 Event::FocusLost => (),     // This can be handled later
-                Event::Paste(_text) => (),  // This can be handled later
-                Event::Resize(_width, _height) => (), // This can be handled later
+                Event::Paste(text) => pasted = Some(text),
+                // Nothing was typed, the windows are only drawn again
+                Event::Resize(_width, _height) => {
+                    fit_windows(
+                        &mut docs,
+                        &mut windows,
+                        &layout,
+                        active,
+                        &settings,
+                        &mut cols,
+                    );
+                    let search = lastsearch.as_ref().filter(|_| hlsearch);
+                    let views = get_views(
+                        &docs,
+                        &windows,
+                        &layout,
+                        active,
+                        visual,
+                        search.map(|s| &s.pattern),
+                        cols,
+                    );
+                    draw(&views, cols, &command, &infotext)?;
+                    continue;
+                }
             };
-            command.push_str(&keycode.clone().to_string());
+            match pasted.take() {
+                // On the command line, pasted text is taken as if it was typed
+                Some(text) if command.starts_with(['/', '?', ':']) => command.push_str(&text),
+                // Otherwise it is handled on its own, see below
+                Some(text) => pasted = Some(text),
+                None => command.push(keycode),
+            }
         } else {
             command.push(autoparse.chars().next().unwrap());
             autoparse.remove(0);
         }
 
-        let parsethisstring = command.clone();
-        let cmd = CmdParser::parse(Rule::cmd_list, &parsethisstring)
+        // While pasting, the command is not parsed again, so a pending
+        // command like an insert is not repeated
+        let parsethisstring = match pasted {
+            Some(_) => String::new(),
+            None => command.clone(),
+        };
+        // In visual mode, other commands are available
+        let list = match (visual, &substitute) {
+            (_, Some(_)) => Rule::confirm_list,
+            (Some(_), None) => Rule::visual_list,
+            (None, None) => Rule::cmd_list,
+        };
+        let cmd = CmdParser::parse(list, &parsethisstring)
             .unwrap()
             .next()
             .unwrap();
 
         let mut clear = true;
         let mut save = false;
+        let mut saveas: Option<PathBuf> = None;
+        let mut newsplit: Option<Split> = None;
+        let mut closewindow = false;
+        let mut only = false;
+        let mut keepregister = false;
 
         // Info can always be cleared as soon as there is a new input
         infotext.clear();
+        let rects = layout.rects(screen_area());
+        let split = windows.len() > 1;
+        // The window compared with in diff mode
+        let partner = diff_partner(&windows, active).map(|p| windows[p]);
+        // Index of the document being edited
+        let mut current = windows[active].doc;
+        // Other documents with unsaved changes prevent quitting
+        let modified_other = docs
+            .iter()
+            .enumerate()
+            .find(|(i, d)| *i != current && d.is_modified())
+            .map(|(_, d)| d.name());
+        let win = &mut windows[active];
+        let doc = &mut docs[win.doc];
+        doc.history.prepare(win.cursor);
+        let selection = visual
+            .map(|v| v.range(win.cursor.pos(), cols, doc.buf.len()))
+            .unwrap_or_default();
+
+        // Hex is decoded when the cursor is over hex, else the text is taken as is
+        if let Some(text) = pasted {
+            let data = match win.cursor.is_over_ascii() {
+                true => Some(text.into_bytes()),
+                false => clipboard::parse_hex(&text),
+            };
+            match data {
+                None => infotext.push_str("Pasted text is not hex"),
+                Some(data) if command.starts_with('i') => {
+                    // A byte with only the left nibble typed is kept as it is
+                    let mut pos = win.cursor.pos();
+                    if win.cursor.is_over_right_nibble() {
+                        pos += 1;
+                        win.cursor.select_left_nibble();
+                    }
+                    let pos = cmp::min(pos, doc.buf.len());
+                    doc.history.insert(&mut doc.buf, pos, &data);
+                    // Insert mode continues behind the pasted bytes
+                    win.cursor.set_pos(pos + data.len());
+                }
+                Some(_) if command == "r" && settings.readonly => {
+                    infotext.push_str("Can not change data in read-only mode");
+                }
+                Some(mut data) if command == "r" => {
+                    // Replacing overwrites the bytes from the cursor on
+                    let pos = cmp::min(win.cursor.pos(), doc.buf.len());
+                    if settings.fixedsize {
+                        data.truncate(doc.buf.len() - pos);
+                    }
+                    doc.history.replace(&mut doc.buf, pos, &data);
+                    command.clear();
+                    doc.history.close(win.cursor);
+                }
+                Some(data) => {
+                    // Otherwise the bytes can be put with 'p'
+                    infotext.push_str(&format!("{} bytes pasted into register", data.len()));
+                    registers.pasted(data);
+                }
+            }
+        }
 
         match cmd.as_rule() {
             Rule::down => {
                 let amount: usize = cmd.as_str().parse().unwrap_or(1);
-                cursor.move_n_down(amount, COLS, buf.len());
+                win.cursor.move_n_down(amount, cols, doc.buf.len());
             }
             Rule::up => {
                 let amount: usize = cmd.as_str().parse().unwrap_or(1);
-                cursor.move_n_up(amount, COLS, buf.len());
+                win.cursor.move_n_up(amount, cols, doc.buf.len());
             }
             Rule::left => {
                 let amount: usize = cmd.as_str().parse().unwrap_or(1);
-                cursor.move_n_left(amount);
+                win.cursor.move_n_left(amount);
             }
             Rule::right => {
                 let amount: usize = cmd.as_str().parse().unwrap_or(1);
-                cursor.move_n_right(amount, buf.len());
+                win.cursor.move_n_right(amount, doc.buf.len());
             }
             Rule::bottom => {
-                let lastline = cursor.get_last_line(COLS, buf.len());
+                let lastline = win.cursor.get_last_line(cols, doc.buf.len());
                 let line: usize = cmd.as_str().parse().unwrap_or(lastline);
-                cursor.move_to_line(line, COLS, buf.len());
+                win.cursor.move_to_line(line, cols, doc.buf.len());
             }
             Rule::top => {
                 let line: usize = cmd.as_str().parse().unwrap_or(0);
-                cursor.move_to_line(line, COLS, buf.len());
+                win.cursor.move_to_line(line, cols, doc.buf.len());
             }
             Rule::start => {
-                cursor.jump_to_start_of_line(COLS);
+                win.cursor.jump_to_start_of_line(cols);
             }
             Rule::end => {
-                cursor.jump_to_end_of_line(COLS, buf.len());
+                win.cursor.jump_to_end_of_line(cols, doc.buf.len());
             }
             Rule::replace => {
                 clear = false;
             }
+            Rule::replacement
+            | Rule::remove
+            | Rule::remove_left
+            | Rule::remove_right
+            | Rule::remove_down
+            | Rule::remove_up
+            | Rule::dd
+            | Rule::bigd
+            | Rule::insert
+            | Rule::diffget
+            | Rule::diffput
+            | Rule::vdelete
+            | Rule::vchange
+            | Rule::vreplace
+            | Rule::vtoggle
+            | Rule::vlower
+            | Rule::vupper
+            | Rule::put
+            | Rule::putbefore
+            | Rule::substitute
+                if settings.readonly =>
+            {
+                infotext.push_str("Can not change data in read-only mode");
+            }
+            Rule::replacement if settings.fixedsize && win.cursor.pos() >= doc.buf.len() => {
+                infotext.push_str("Can not append, the size is fixed");
+            }
             Rule::replacement => {
                 let key = command.chars().last().unwrap_or('x');
 
                 // Allow inserting stuff behind end of buffer
-                if cursor.pos() >= buf.len() {
-                    buf.insert(cursor.pos(), 0);
+                if win.cursor.pos() >= doc.buf.len() {
+                    doc.history.insert(&mut doc.buf, win.cursor.pos(), &[0]);
                 }
                 // Insert the key at the selected position
-                match cursor.selects() {
+                let mut byte = doc.buf.get(win.cursor.pos()).unwrap_or(0);
+                match win.cursor.selects() {
                     CursorSelects::AsciiChar => {
-                        buf[cursor.pos()] = key as u8;
+                        byte = key as u8;
                     }
                     CursorSelects::LeftNibble => {
                         if let Some(c) = key.to_digit(16) {
-                            buf[cursor.pos()] = buf[cursor.pos()] & 0x0F | (c as u8) << 4;
+                            byte = byte & 0x0F | (c as u8) << 4;
                         }
                     }
                     CursorSelects::RightNibble => {
                         if let Some(c) = key.to_digit(16) {
-                            buf[cursor.pos()] = buf[cursor.pos()] & 0xF0 | (c as u8);
+                            byte = byte & 0xF0 | (c as u8);
                         }
                     }
                 }
+                doc.history.replace(&mut doc.buf, win.cursor.pos(), &[byte]);
                 lastcommand = command.clone();
             }
             Rule::remove => {
                 let amount: usize = cmd.as_str().parse().unwrap_or(1);
-                let mut start = cursor.pos();
+                let mut start = win.cursor.pos();
                 let mut end = start + amount;
-                start = cmp::min(start, buf.len());
-                end = cmp::min(end, buf.len());
-                buf.drain(start..end);
+                start = cmp::min(start, doc.buf.len());
+                end = cmp::min(end, doc.buf.len());
+                let removed = delete(
+                    &mut doc.buf,
+                    &mut doc.history,
+                    start..end,
+                    settings.fixedsize,
+                );
+                registers.deleted(register, removed, false);
                 // Move cursor if it is out of data
-                cursor.trim_to_max_minus_one(buf.len());
+                win.cursor.trim_to_max_minus_one(doc.buf.len());
                 lastcommand = command.clone();
             }
             Rule::remove_left => {
                 let amount: usize = cmd.into_inner().as_str().parse().unwrap_or(1);
-                let mut end = cursor.pos();
+                let mut end = win.cursor.pos();
                 // The function move_n_left() in hex mode removes only half of
                 // what one might expect. It feels quite unnatural. Also how
                 // it should be implemented is not fully clear atm. Therefore
                 // we use this remove command always in ascii style.
                 // Not used: cursor.move_n_left(...), Instead: cursor.sub(...)
-                cursor.sub(amount, 0);
-                if !cursor.is_over_ascii() {
-                    infotext.push_str(&format!("Warning, delete is operating on whole bytes.",));
-                }
-                let mut start = cursor.pos();
-                start = cmp::min(start, buf.len());
-                end = cmp::min(end, buf.len());
-                buf.drain(start..end);
+                win.cursor.sub(amount, 0);
+                if !win.cursor.is_over_ascii() {
+                    infotext.push_str("Warning, delete is operating on whole bytes.");
+                }
+                let mut start = win.cursor.pos();
+                start = cmp::min(start, doc.buf.len());
+                end = cmp::min(end, doc.buf.len());
+                let removed = delete(
+                    &mut doc.buf,
+                    &mut doc.history,
+                    start..end,
+                    settings.fixedsize,
+                );
+                registers.deleted(register, removed, false);
                 // Move cursor if it is out of data
-                cursor.trim_to_max_minus_one(buf.len());
+                win.cursor.trim_to_max_minus_one(doc.buf.len());
                 lastcommand = command.clone();
             }
             Rule::remove_right => {
                 let amount: usize = cmd.into_inner().as_str().parse().unwrap_or(1);
-                let mut start = cursor.pos();
+                let mut start = win.cursor.pos();
                 // The function move_n_right() in hex mode removes only half of
                 // what one might expect. It feels quite unnatural. Also how
                 // it should be implemented is not fully clear atm. Therefore
                 // we use this remove command always in ascii style.
                 // Not used: cursor.move_n_right(...), Instead: cursor.add(...)
-                cursor.add(amount, buf.len());
-                if !cursor.is_over_ascii() {
-                    infotext.push_str(&format!("Warning, delete is operating on whole bytes.",));
-                }
-                let mut end = cursor.pos();
-                start = cmp::min(start, buf.len());
-                end = cmp::min(end, buf.len());
-                buf.drain(start..end);
+                win.cursor.add(amount, doc.buf.len());
+                if !win.cursor.is_over_ascii() {
+                    infotext.push_str("Warning, delete is operating on whole bytes.");
+                }
+                let mut end = win.cursor.pos();
+                start = cmp::min(start, doc.buf.len());
+                end = cmp::min(end, doc.buf.len());
+                let removed = delete(
+                    &mut doc.buf,
+                    &mut doc.history,
+                    start..end,
+                    settings.fixedsize,
+                );
+                registers.deleted(register, removed, false);
                 // Cursor should stay at original position
-                cursor.set_pos(start);
+                win.cursor.set_pos(start);
                 lastcommand = command.clone();
             }
             Rule::remove_down => {
                 let amount: usize = cmd.into_inner().as_str().parse().unwrap_or(1);
-                let initial = cursor.pos();
-                let mut start = cursor.calculate_start_of_line(COLS);
-                cursor.move_n_down(amount, COLS, buf.len());
+                let initial = win.cursor.pos();
+                let mut start = win.cursor.calculate_start_of_line(cols);
+                win.cursor.move_n_down(amount, cols, doc.buf.len());
                 // One more as we also want to delete the last character
-                let mut end = cursor.calculate_end_of_line(COLS) + 1;
-                start = cmp::min(start, buf.len());
-                end = cmp::min(end, buf.len());
-                buf.drain(start..end);
+                let mut end = win.cursor.calculate_end_of_line(cols) + 1;
+                start = cmp::min(start, doc.buf.len());
+                end = cmp::min(end, doc.buf.len());
+                let removed = delete(
+                    &mut doc.buf,
+                    &mut doc.history,
+                    start..end,
+                    settings.fixedsize,
+                );
+                registers.deleted(register, removed, true);
                 // Cursor should stay at original position
-                cursor.set_pos(initial);
+                win.cursor.set_pos(initial);
                 // Move cursor if it is out of data
-                cursor.trim_to_max_minus_one(buf.len());
+                win.cursor.trim_to_max_minus_one(doc.buf.len());
                 lastcommand = command.clone();
             }
             Rule::remove_up => {
                 let amount: usize = cmd.into_inner().as_str().parse().unwrap_or(1);
                 // One more as we also want to delete the last character
-                let mut end = cursor.calculate_end_of_line(COLS) + 1;
-                cursor.move_n_up(amount, COLS, buf.len());
-                let mut start = cursor.calculate_start_of_line(COLS);
-                start = cmp::min(start, buf.len());
-                end = cmp::min(end, buf.len());
-                buf.drain(start..end);
+                let mut end = win.cursor.calculate_end_of_line(cols) + 1;
+                win.cursor.move_n_up(amount, cols, doc.buf.len());
+                let mut start = win.cursor.calculate_start_of_line(cols);
+                start = cmp::min(start, doc.buf.len());
+                end = cmp::min(end, doc.buf.len());
+                let removed = delete(
+                    &mut doc.buf,
+                    &mut doc.history,
+                    start..end,
+                    settings.fixedsize,
+                );
+                registers.deleted(register, removed, true);
                 // Move cursor if it is out of data
-                cursor.trim_to_max_minus_one(buf.len());
+                win.cursor.trim_to_max_minus_one(doc.buf.len());
                 lastcommand = command.clone();
             }
             Rule::dd => {
                 let amount: usize = cmd.as_str().parse().unwrap_or(1);
-                let mut start = cursor.calculate_start_of_line(COLS);
-                let mut end = start + (COLS * amount);
-                start = cmp::min(start, buf.len());
-                end = cmp::min(end, buf.len());
-                buf.drain(start..end);
+                let mut start = win.cursor.calculate_start_of_line(cols);
+                let mut end = start + (cols * amount);
+                start = cmp::min(start, doc.buf.len());
+                end = cmp::min(end, doc.buf.len());
+                let removed = delete(
+                    &mut doc.buf,
+                    &mut doc.history,
+                    start..end,
+                    settings.fixedsize,
+                );
+                registers.deleted(register, removed, true);
                 // Move cursor if it is out of data
-                cursor.trim_to_max_minus_one(buf.len());
+                win.cursor.trim_to_max_minus_one(doc.buf.len());
                 lastcommand = command.clone();
             }
             Rule::bigd => {
-                let mut start = cursor.pos();
+                let mut start = win.cursor.pos();
                 // One more as we also want to delete the last character
-                let mut end = cursor.calculate_end_of_line(COLS) + 1;
-                start = cmp::min(start, buf.len());
-                end = cmp::min(end, buf.len());
-                buf.drain(start..end);
+                let mut end = win.cursor.calculate_end_of_line(cols) + 1;
+                start = cmp::min(start, doc.buf.len());
+                end = cmp::min(end, doc.buf.len());
+                let removed = delete(
+                    &mut doc.buf,
+                    &mut doc.history,
+                    start..end,
+                    settings.fixedsize,
+                );
+                registers.deleted(register, removed, false);
                 // Move cursor if it is out of data
-                cursor.trim_to_max_minus_one(buf.len());
+                win.cursor.trim_to_max_minus_one(doc.buf.len());
                 lastcommand = command.clone();
             }
             Rule::insert => {
-                // The next chars will be inserted
-                clear = false;
+                if settings.fixedsize {
+                    infotext.push_str("Can not insert, the size is fixed");
+                } else {
+                    // The next chars will be inserted
+                    clear = false;
+                }
             }
             Rule::insertstuff => {
                 let key = command.chars().last().unwrap_or('x');
 
-                match cursor.selects() {
+                match win.cursor.selects() {
                     CursorSelects::LeftNibble => {
                         if let Some(c) = key.to_digit(16) {
-                            buf.insert(cursor.pos(), (c as u8) << 4);
-                            cursor.select_right_nibble();
+                            doc.history
+                                .insert(&mut doc.buf, win.cursor.pos(), &[(c as u8) << 4]);
+                            win.cursor.select_right_nibble();
                         }
                     }
                     CursorSelects::RightNibble => {
                         // This if checks if we are out of range already
-                        if cursor.pos() == buf.len() {
+                        if win.cursor.pos() == doc.buf.len() {
                             // Then just insert some data
-                            buf.insert(cursor.pos(), 0);
+                            doc.history.insert(&mut doc.buf, win.cursor.pos(), &[0]);
                         }
                         if let Some(c) = key.to_digit(16) {
-                            buf[cursor.pos()] = buf[cursor.pos()] & 0xF0 | c as u8;
-                            cursor.select_left_nibble();
+                            let byte = doc.buf.get(win.cursor.pos()).unwrap_or(0) & 0xF0 | c as u8;
+                            doc.history.replace(&mut doc.buf, win.cursor.pos(), &[byte]);
+                            win.cursor.select_left_nibble();
                             // This puts the cursor out of range intentionally,
                             // inserting nibbles would feel strange otherwise.
-                            cursor.add(1, buf.len() + 1);
+                            win.cursor.add(1, doc.buf.len() + 1);
                         }
                     }
                     CursorSelects::AsciiChar => {
-                        buf.insert(cursor.pos(), key as u8);
+                        doc.history
+                            .insert(&mut doc.buf, win.cursor.pos(), &[key as u8]);
                         // This puts the cursor out of range intentionally,
                         // this is probably later used by the command 'a'
-                        cursor.add(1, buf.len() + 1);
+                        win.cursor.add(1, doc.buf.len() + 1);
                     }
                 }
 
@@ -310,70 +775,676 @@ fn main() -> Result<(), Error> {
             Rule::insertend => {
                 lastcommand = command.clone();
             }
+            Rule::visual | Rule::visualline => {
+                let lines = cmd.as_rule() == Rule::visualline;
+                visual = match visual {
+                    // The same key again ends visual mode
+                    Some(v) if v.lines == lines => None,
+                    Some(v) => Some(Visual { lines, ..v }),
+                    None => Some(Visual {
+                        start: win.cursor.pos(),
+                        lines,
+                    }),
+                };
+            }
+            Rule::escape if visual.is_some() => visual = None,
+            Rule::vswap => {
+                if let Some(v) = visual.as_mut() {
+                    let start = v.start;
+                    v.start = win.cursor.pos();
+                    win.cursor.set_pos(start);
+                }
+            }
+            Rule::vdelete => {
+                let removed = delete(
+                    &mut doc.buf,
+                    &mut doc.history,
+                    selection.clone(),
+                    settings.fixedsize,
+                );
+                registers.deleted(register, removed, visual.is_some_and(|v| v.lines));
+                win.cursor.set_pos(selection.start);
+                win.cursor.trim_to_max_minus_one(doc.buf.len());
+                visual = None;
+            }
+            Rule::vchange if settings.fixedsize => {
+                infotext.push_str("Can not insert, the size is fixed");
+            }
+            Rule::vchange => {
+                let removed = doc.history.remove(&mut doc.buf, selection.clone());
+                registers.deleted(register, removed, visual.is_some_and(|v| v.lines));
+                win.cursor.set_pos(selection.start);
+                if !win.cursor.is_over_ascii() {
+                    win.cursor.select_left_nibble();
+                }
+                visual = None;
+                // Continue in insert mode, like after 'i'
+                command = String::from("i");
+                clear = false;
+            }
+            Rule::vreplace => {
+                let key = command.chars().last().unwrap_or('x');
+                let selects = win.cursor.selects();
+                convert(&mut doc.buf, &mut doc.history, selection.clone(), |byte| {
+                    match (selects, key.to_digit(16)) {
+                        (CursorSelects::AsciiChar, _) => key as u8,
+                        (CursorSelects::LeftNibble, Some(c)) => byte & 0x0F | (c as u8) << 4,
+                        (CursorSelects::RightNibble, Some(c)) => byte & 0xF0 | c as u8,
+                        // Only hex digits can replace nibbles
+                        (_, None) => byte,
+                    }
+                });
+                win.cursor.set_pos(selection.start);
+                visual = None;
+            }
+            Rule::vtoggle | Rule::vlower | Rule::vupper => {
+                let rule = cmd.as_rule();
+                convert(
+                    &mut doc.buf,
+                    &mut doc.history,
+                    selection.clone(),
+                    |byte| match rule {
+                        Rule::vlower => byte.to_ascii_lowercase(),
+                        Rule::vupper => byte.to_ascii_uppercase(),
+                        _ if byte.is_ascii_lowercase() => byte.to_ascii_uppercase(),
+                        _ => byte.to_ascii_lowercase(),
+                    },
+                );
+                win.cursor.set_pos(selection.start);
+                visual = None;
+            }
+            Rule::regname => {
+                register = cmd.as_str().chars().nth(1);
+                keepregister = true;
+            }
+            Rule::yank_left | Rule::yank_right | Rule::yank_down | Rule::yank_up | Rule::yy => {
+                let amount: usize = match cmd.as_rule() {
+                    Rule::yy => cmd.as_str().parse().unwrap_or(1),
+                    _ => cmd.clone().into_inner().as_str().parse().unwrap_or(1),
+                };
+                let pos = win.cursor.pos();
+                let line = win.cursor.calculate_start_of_line(cols);
+                // The same bytes the delete with the same motion removes
+                let range = match cmd.as_rule() {
+                    Rule::yank_left => pos.saturating_sub(amount)..pos,
+                    Rule::yank_right => pos..pos.saturating_add(amount),
+                    Rule::yank_down => {
+                        line..line.saturating_add(cols.saturating_mul(amount.saturating_add(1)))
+                    }
+                    Rule::yank_up => line.saturating_sub(cols.saturating_mul(amount))..line + cols,
+                    _ => line..line.saturating_add(cols.saturating_mul(amount)),
+                };
+                let range =
+                    cmp::min(range.start, doc.buf.len())..cmp::min(range.end, doc.buf.len());
+                let lines = !matches!(cmd.as_rule(), Rule::yank_left | Rule::yank_right);
+                registers.yanked(register, doc.buf.read(range.clone()), lines);
+                // Yanking backwards moves the cursor to the start
+                if matches!(cmd.as_rule(), Rule::yank_left | Rule::yank_up) {
+                    win.cursor.set_pos(cmp::min(range.start, doc.buf.len()));
+                }
+            }
+            Rule::vyank => {
+                let lines = visual.is_some_and(|v| v.lines);
+                registers.yanked(register, doc.buf.read(selection.clone()), lines);
+                win.cursor.set_pos(selection.start);
+                visual = None;
+            }
+            Rule::put | Rule::putbefore if settings.fixedsize => {
+                infotext.push_str("Can not insert, the size is fixed");
+            }
+            Rule::put | Rule::putbefore => {
+                let amount: usize = cmd.as_str().parse().unwrap_or(1);
+                match registers.get(register).map(|r| (r, r.repeated(amount))) {
+                    Some((_, None)) => infotext.push_str("Can not put, that is too much data"),
+                    Some((r, Some(data))) => {
+                        let after = cmd.as_rule() == Rule::put;
+                        // Lines are put below or above the line of the cursor
+                        let pos = match (r.lines, after) {
+                            (true, true) => win.cursor.calculate_start_of_line(cols) + cols,
+                            (true, false) => win.cursor.calculate_start_of_line(cols),
+                            (false, true) => win.cursor.pos() + 1,
+                            (false, false) => win.cursor.pos(),
+                        };
+                        let pos = cmp::min(pos, doc.buf.len());
+                        doc.history.insert(&mut doc.buf, pos, &data);
+                        if r.lines {
+                            win.cursor.set_pos(pos);
+                        } else {
+                            win.cursor.set_pos((pos + data.len()).saturating_sub(1));
+                        }
+                        lastcommand = command.clone();
+                    }
+                    None => infotext.push_str(&format!(
+                        "E353: Nothing in register {}",
+                        register.unwrap_or('"')
+                    )),
+                }
+            }
+            Rule::listregisters => {
+                let list: Vec<String> = registers
+                    .list()
+                    .iter()
+                    .map(|(name, r)| {
+                        let mut hex: Vec<String> = r
+                            .data
+                            .iter()
+                            .take(16)
+                            .map(|b| format!("{:02X}", b))
+                            .collect();
+                        if r.data.len() > 16 {
+                            hex.push(String::from("..."));
+                        }
+                        format!("\"{} {}", name, hex.join(" "))
+                    })
+                    .collect();
+                infotext.push_str(&list.join(", "));
+            }
             Rule::jumpascii => {
-                cursor.swap_selection_hex_ascii();
+                win.cursor.swap_selection_hex_ascii();
             }
             Rule::querry => {
                 // Most likely will be changed later
-                infotext.push_str(&format!("Current byte marked: {}", cursor.pos()));
+                infotext.push_str(&format!("Current byte marked: {}", win.cursor.pos()));
             }
             Rule::repeat => {
                 autoparse = lastcommand.clone();
             }
-            Rule::searchend => {
-                if cursor.is_over_ascii() {
-                    let searchstr = cmd.clone().into_inner().as_str();
-                    let search = searchstr.as_bytes();
-                    let newpos = match TwoWaySearcher::new(search).search_in(&buf) {
-                        Some(t) => t,
+            Rule::undo => {
+                let amount: usize = cmd.as_str().parse().unwrap_or(1);
+                for _ in 0..amount {
+                    match doc.history.undo(&mut doc.buf) {
+                        Some(c) => win.cursor = c,
                         None => {
-                            infotext.push_str(&format!("Pattern not found: {}", searchstr));
-                            cursor.pos() // Return same position
+                            infotext.push_str("Already at oldest change");
+                            break;
                         }
-                    };
-                    cursor.set_pos(newpos);
-                } else {
-                    infotext.push_str("Ascii-search works, when the cursor is over ascii");
-                    command.pop();
-                    clear = false;
+                    }
                 }
             }
-            Rule::hexsearchend => {
-                if cursor.is_over_ascii() {
-                    let searchstr = cmd.clone().into_inner().as_str();
-                    let search = searchstr.as_bytes();
-                    let newpos = match TwoWaySearcher::new(search).search_in(&buf) {
-                        Some(t) => t,
+            Rule::redo => {
+                let amount: usize = cmd.as_str().parse().unwrap_or(1);
+                for _ in 0..amount {
+                    match doc.history.redo(&mut doc.buf) {
+                        Some(c) => win.cursor = c,
                         None => {
-                            infotext.push_str(&format!("Pattern not found: {}", searchstr));
-                            cursor.pos() // Return same position
+                            infotext.push_str("Already at newest change");
+                            break;
                         }
-                    };
-                    cursor.set_pos(newpos);
-                } else {
-                    let searchstr = cmd.clone().into_inner().as_str();
-                    let search = searchstr.as_bytes();
-                    let newpos = match buf.search(search) {
-                        Some(t) => t,
-                        None => {
-                            infotext.push_str(&format!("Pattern not found: {}", searchstr));
-                            cursor.pos() // Return same position
+                    }
+                }
+            }
+            Rule::searchend | Rule::hexsearchend => {
+                let backward = cmd.as_str().starts_with('?');
+                let searchstr = String::from(cmd.clone().into_inner().as_str());
+                let options = TextOptions {
+                    encoding: settings.searchenc,
+                    ignorecase: settings.ignorecase,
+                };
+                let (options, text) = options.strip_flags(&searchstr);
+                // An empty pattern searches for the last one again
+                let pattern = match (&lastsearch, searchstr.is_empty()) {
+                    (Some(last), true) => Ok(last.pattern.clone()),
+                    (None, true) => Err(String::from("E35: No previous regular expression")),
+                    // In the hex column, hex digits are searched as signature
+                    _ if cmd.as_rule() == Rule::hexsearchend && !win.cursor.is_over_ascii() => {
+                        Pattern::hex(&searchstr)
+                    }
+                    _ => Pattern::regex(text, options),
+                };
+                match pattern {
+                    Ok(pattern) => {
+                        let search = LastSearch { pattern, backward };
+                        let pos = find_match(
+                            &doc.buf,
+                            win.cursor.pos(),
+                            &search,
+                            backward,
+                            1,
+                            &mut infotext,
+                        );
+                        win.cursor.set_pos(pos);
+                        lastsearch = Some(search);
+                        hlsearch = true;
+                    }
+                    Err(e) => infotext.push_str(&e),
+                }
+            }
+            Rule::searchnext | Rule::searchprev => match &lastsearch {
+                Some(search) => {
+                    let amount: usize = cmd.as_str().parse().unwrap_or(1);
+                    // N searches in the opposite direction
+                    let backward = search.backward != (cmd.as_rule() == Rule::searchprev);
+                    let pos = find_match(
+                        &doc.buf,
+                        win.cursor.pos(),
+                        search,
+                        backward,
+                        amount,
+                        &mut infotext,
+                    );
+                    win.cursor.set_pos(pos);
+                    hlsearch = true;
+                }
+                None => infotext.push_str("E35: No previous regular expression"),
+            },
+            Rule::substitute => {
+                let text = find_token(&cmd, Rule::subpattern).map_or("", |p| p.as_str());
+                let options = TextOptions {
+                    encoding: settings.searchenc,
+                    ignorecase: settings.ignorecase,
+                };
+                let (options, text) = options.strip_flags(text);
+                let newtext = find_token(&cmd, Rule::subreplacement).map_or("", |p| p.as_str());
+                let flags = find_token(&cmd, Rule::subflags).map_or("", |p| p.as_str());
+                // In the hex column, hex digits are nibbles, like when searching
+                let is_hex = |t: &str, signature: &str| {
+                    !win.cursor.is_over_ascii()
+                        && t.chars().all(|c| {
+                            c.is_ascii_hexdigit() || c == 'x' || c == 'X' || signature.contains(c)
+                        })
+                };
+                let pattern = match (&lastsearch, text.is_empty()) {
+                    (Some(last), true) => Ok(last.pattern.clone()),
+                    (None, true) => Err(String::from("E35: No previous regular expression")),
+                    _ if is_hex(text, "? &[]-()|") => Pattern::hex(text),
+                    _ => Pattern::regex(text, options),
+                };
+                // The replacement is encoded like the pattern
+                let replacement = match (is_hex(newtext, " ?"), options.encoding) {
+                    (true, _) => Some(Replacement::hex(newtext)),
+                    (false, Encoding::Utf8) => Replacement::ascii(newtext),
+                    (false, encoding) => encoding.encode(newtext).map(Replacement::Ascii),
+                };
+                // Without a range, only the line of the cursor
+                let range = match find_token(&cmd, Rule::range) {
+                    Some(_) => get_range(&cmd, cols, doc.buf.len()),
+                    None => {
+                        let start = win.cursor.calculate_start_of_line(cols);
+                        start..cmp::min(start + cols, doc.buf.len())
+                    }
+                };
+                match (pattern, replacement) {
+                    (Err(e), _) => infotext.push_str(&e),
+                    (_, None) => infotext.push_str(&format!("Invalid replacement: {}", newtext)),
+                    (Ok(pattern), Some(replacement)) => {
+                        let global = flags.contains('g');
+                        let mut s =
+                            Substitute::new(&pattern, replacement, &doc.buf, range, global, cols);
+                        if s.current().is_none() {
+                            infotext.push_str(&format!("Pattern not found: {}", pattern.text()));
+                        } else if settings.fixedsize && s.resizes() {
+                            infotext.push_str("Can not resize, the size is fixed");
+                        } else if flags.contains('c') {
+                            substitute = Some(s);
+                        } else {
+                            s.replace_all(&mut doc.buf, &mut doc.history);
+                            infotext.push_str(&s.summary());
                         }
-                    };
-                    cursor.set_pos(newpos);
+                        // Like in vim, n and N search for the pattern
+                        lastsearch = Some(LastSearch {
+                            pattern,
+                            backward: false,
+                        });
+                        hlsearch = true;
+                    }
+                }
+            }
+            Rule::find | Rule::findall => {
+                let kind = find_token(&cmd, Rule::valuetype).map_or("", |p| p.as_str());
+                let text = find_token(&cmd, Rule::value).map_or("", |p| p.as_str());
+                // Searched for like the hex digits typed after '/'
+                let pattern = value::encode(kind, text).and_then(|bytes| {
+                    let hex: String = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+                    Pattern::hex(&hex).ok()
+                });
+                match pattern {
+                    Some(pattern) => {
+                        let search = LastSearch {
+                            pattern,
+                            backward: false,
+                        };
+                        if cmd.as_rule() == Rule::find {
+                            let pos = find_match(
+                                &doc.buf,
+                                win.cursor.pos(),
+                                &search,
+                                false,
+                                1,
+                                &mut infotext,
+                            );
+                            win.cursor.set_pos(pos);
+                        } else {
+                            let (count, starts) = search.pattern.count_matches(&doc.buf, 16);
+                            let mut list: Vec<String> = starts
+                                .iter()
+                                .map(|start| format!("{:08X}", start))
+                                .collect();
+                            if count > starts.len() {
+                                list.push(String::from("..."));
+                            }
+                            match count {
+                                0 => infotext.push_str(&format!(
+                                    "Pattern not found: {}",
+                                    search.pattern.text()
+                                )),
+                                n => infotext.push_str(&format!(
+                                    "{} matches: {}",
+                                    n,
+                                    list.join(", ")
+                                )),
+                            }
+                        }
+                        lastsearch = Some(search);
+                        hlsearch = true;
+                    }
+                    None => infotext.push_str(&format!("Invalid value: {} {}", kind, text)),
+                }
+            }
+            Rule::confirmyes | Rule::confirmno | Rule::confirmall | Rule::confirmlast => {
+                if let Some(s) = substitute.as_mut() {
+                    match cmd.as_rule() {
+                        Rule::confirmyes => s.replace(&mut doc.buf, &mut doc.history),
+                        Rule::confirmno => s.skip(),
+                        Rule::confirmall => s.replace_all(&mut doc.buf, &mut doc.history),
+                        _ => {
+                            s.replace(&mut doc.buf, &mut doc.history);
+                            s.stop();
+                        }
+                    }
+                }
+            }
+            Rule::confirmquit => {
+                if let Some(s) = substitute.as_mut() {
+                    s.stop();
                 }
             }
+            Rule::nohlsearch => {
+                hlsearch = false;
+            }
             Rule::backspace => {
                 command.pop();
                 command.pop();
                 clear = false;
             }
+            Rule::save
+                if doc.path.is_none()
+                    && get_filename(&cmd).is_some()
+                    && find_token(&cmd, Rule::range).is_none()
+                    && find_token(&cmd, Rule::append).is_none() =>
+            {
+                // Without a name, the file given becomes the edited file
+                let name = get_filename(&cmd).unwrap_or_default();
+                if name.exists() && !is_forced(&cmd) {
+                    infotext.push_str("E13: File exists (add ! to override)");
+                } else {
+                    saveas = Some(name);
+                }
+            }
+            Rule::save
+                if get_filename(&cmd).is_some_and(|name| {
+                    !doc.path.as_ref().is_some_and(|p| file::same_file(&name, p))
+                        || find_token(&cmd, Rule::append).is_some()
+                }) =>
+            {
+                let name = get_filename(&cmd).unwrap_or_default();
+                let range = get_range(&cmd, cols, doc.buf.len());
+                let append = find_token(&cmd, Rule::append).is_some();
+                if name.exists() && !append && !is_forced(&cmd) {
+                    infotext.push_str("E13: File exists (add ! to override)");
+                } else {
+                    match file::write(&name, &doc.buf, range.clone(), append) {
+                        Ok(()) => infotext.push_str(&format!(
+                            "{} bytes written to {}",
+                            range.len(),
+                            name.display()
+                        )),
+                        Err(e) => {
+                            infotext.push_str(&format!("Careful, file could not be saved! {}", e))
+                        }
+                    }
+                }
+            }
+            Rule::save if find_token(&cmd, Rule::range).is_some() => {
+                infotext.push_str("Writing only some lines needs a file name");
+            }
+            Rule::saveas => {
+                let name = get_filename(&cmd).unwrap_or_default();
+                if name.exists() && !is_forced(&cmd) {
+                    infotext.push_str("E13: File exists (add ! to override)");
+                } else {
+                    saveas = Some(name);
+                }
+            }
+            Rule::saveandexit | Rule::save if settings.readonly && !is_forced(&cmd) => {
+                infotext.push_str("E45: 'readonly' option is set (add ! to override)");
+            }
+            // With several windows, only the window gets closed
+            Rule::exit if split => closewindow = true,
+            Rule::saveandexit if split => {
+                save = true;
+                closewindow = true;
+            }
+            Rule::exit | Rule::saveandexit if modified_other.is_some() && !is_forced(&cmd) => {
+                infotext.push_str(&format!(
+                    "E162: No write since last change for buffer \"{}\"",
+                    modified_other.unwrap_or_default()
+                ));
+            }
             Rule::saveandexit => {
                 save = true;
                 quitnow = true;
             }
+            Rule::exit if doc.is_modified() && !is_forced(&cmd) => {
+                infotext.push_str("E37: No write since last change (add ! to override)");
+            }
             Rule::exit => quitnow = true,
+            Rule::edit
+                if get_filename(&cmd).is_some_and(|name| {
+                    !doc.path.as_ref().is_some_and(|p| file::same_file(&name, p))
+                }) =>
+            {
+                // Other files are opened as additional document
+                let name = get_filename(&cmd).unwrap_or_default();
+                match open_document(&mut docs, name, settings.readonly, &mut infotext) {
+                    Ok(index) => current = index,
+                    Err(e) => infotext.push_str(&e.to_string()),
+                }
+            }
+            Rule::edit if doc.is_modified() && !is_forced(&cmd) => {
+                infotext.push_str("E37: No write since last change (add ! to override)");
+            }
+            Rule::edit => match doc.path.clone() {
+                // Reload the file, changes get discarded
+                Some(name) => match Document::open(name, settings.readonly) {
+                    Ok(opened) => {
+                        docs[current] = opened;
+                        // Every window showing it starts at the top again
+                        for w in windows.iter_mut().filter(|w| w.doc == current) {
+                            w.cursor = Cursor::default();
+                            w.screenoffset = 0;
+                        }
+                    }
+                    Err(e) => infotext.push_str(&e.to_string()),
+                },
+                None => infotext.push_str("E32: No file name"),
+            },
+            Rule::enew => {
+                docs.push(Document::default());
+                current = docs.len() - 1;
+            }
+            Rule::buffers => {
+                let list: Vec<String> = docs
+                    .iter()
+                    .enumerate()
+                    .map(|(i, d)| {
+                        let mut entry = format!("{} ", i + 1);
+                        if i == current {
+                            entry.push('%');
+                        }
+                        entry.push_str(&d.name());
+                        if d.is_modified() {
+                            entry.push_str(" [+]");
+                        }
+                        entry
+                    })
+                    .collect();
+                infotext.push_str(&list.join(", "));
+            }
+            Rule::bnext => current = (current + 1) % docs.len(),
+            Rule::bprevious => current = (current + docs.len() - 1) % docs.len(),
+            Rule::buffer => {
+                let number: usize = cmd.into_inner().as_str().parse().unwrap_or(0);
+                if (1..=docs.len()).contains(&number) {
+                    current = number - 1;
+                } else {
+                    infotext.push_str(&format!("E86: Buffer {} does not exist", number));
+                }
+            }
+            Rule::bdelete => {
+                let number = match find_token(&cmd, Rule::bufnr) {
+                    Some(nr) => nr.as_str().parse().unwrap_or(0),
+                    None => current + 1,
+                };
+                if !(1..=docs.len()).contains(&number) {
+                    infotext.push_str(&format!("E516: No buffers were deleted: {}", number));
+                } else if docs[number - 1].is_modified() && !is_forced(&cmd) {
+                    infotext.push_str(&format!(
+                        "E89: No write since last change for buffer {} (add ! to override)",
+                        number
+                    ));
+                } else {
+                    let removed = number - 1;
+                    docs.remove(removed);
+                    if docs.is_empty() {
+                        docs.push(Document::default());
+                    }
+                    // Windows showing the deleted document show the next one
+                    for w in windows.iter_mut() {
+                        if w.doc == removed {
+                            w.doc = cmp::min(removed, docs.len() - 1);
+                            w.cursor = docs[w.doc].cursor;
+                            w.screenoffset = docs[w.doc].screenoffset;
+                        } else if w.doc > removed {
+                            w.doc -= 1;
+                        }
+                    }
+                    current = windows[active].doc;
+                }
+            }
+            Rule::split | Rule::vsplit => {
+                newsplit = match cmd.as_rule() {
+                    Rule::split => Some(Split::Horizontal),
+                    _ => Some(Split::Vertical),
+                };
+                if let Some(name) = get_filename(&cmd) {
+                    match open_document(&mut docs, name, settings.readonly, &mut infotext) {
+                        Ok(index) => current = index,
+                        Err(e) => {
+                            infotext.push_str(&e.to_string());
+                            newsplit = None;
+                        }
+                    }
+                }
+            }
+            Rule::wnext => {
+                active = (active + 1) % windows.len();
+                current = windows[active].doc;
+            }
+            Rule::wprevious => {
+                active = (active + windows.len() - 1) % windows.len();
+                current = windows[active].doc;
+            }
+            Rule::wleft | Rule::wdown | Rule::wup | Rule::wright => {
+                let direction = match cmd.as_rule() {
+                    Rule::wleft => Direction::Left,
+                    Rule::wdown => Direction::Down,
+                    Rule::wup => Direction::Up,
+                    _ => Direction::Right,
+                };
+                if let Some(next) = neighbour(&rects, active, direction) {
+                    active = next;
+                    current = windows[active].doc;
+                }
+            }
+            Rule::wclose if !split => {
+                infotext.push_str("E444: Cannot close last window");
+            }
+            Rule::wclose => closewindow = true,
+            Rule::wonly => only = true,
+            Rule::diffthis => win.diff = true,
+            Rule::diffoff if is_forced(&cmd) => {
+                for w in windows.iter_mut() {
+                    w.diff = false;
+                }
+            }
+            Rule::diffoff => win.diff = false,
+            Rule::nextchange | Rule::prevchange | Rule::diffget | Rule::diffput
+                if partner.is_none() =>
+            {
+                infotext.push_str("E99: Current buffer is not in diff mode");
+            }
+            Rule::nextchange | Rule::prevchange => {
+                let amount: usize = cmd.as_str().parse().unwrap_or(1);
+                let this = &docs[win.doc].buf;
+                let other = &docs[partner.unwrap_or_default().doc].buf;
+                let mut pos = win.cursor.pos();
+                for _ in 0..amount {
+                    let found = match cmd.as_rule() {
+                        Rule::nextchange => diff::next_change(this, other, pos),
+                        _ => diff::previous_change(this, other, pos),
+                    };
+                    match found {
+                        Some(p) => pos = p,
+                        None => {
+                            infotext.push_str("No more differences");
+                            break;
+                        }
+                    }
+                }
+                // Changes behind the end are shown at the end
+                win.cursor.set_pos(cmp::min(pos, this.len()));
+            }
+            Rule::diffget | Rule::diffput => {
+                let other = partner.unwrap_or_default();
+                // The document which gets changed and its cursor
+                let (target, source, cursor) = match cmd.as_rule() {
+                    Rule::diffget => (win.doc, other.doc, win.cursor),
+                    _ => (other.doc, win.doc, other.cursor),
+                };
+                if target != source {
+                    let (target, source) = document::pair(&mut docs, target, source);
+                    let range = match find_token(&cmd, Rule::range) {
+                        Some(_) => {
+                            let len = cmp::max(target.buf.len(), source.buf.len());
+                            Some(get_range(&cmd, cols, len))
+                        }
+                        None => diff::change_at(&target.buf, &source.buf, win.cursor.pos()),
+                    };
+                    match range {
+                        Some(range) => {
+                            let data = source.buf.read(range.clone());
+                            let end = cmp::min(range.end, target.buf.len());
+                            let start = cmp::min(range.start, end);
+                            if settings.fixedsize && data.len() != end - start {
+                                infotext.push_str("Can not resize, the size is fixed");
+                            } else {
+                                target.history.prepare(cursor);
+                                target.history.splice(&mut target.buf, start..end, &data);
+                                target.history.close(cursor);
+                            }
+                        }
+                        None => infotext.push_str("No differences at the cursor"),
+                    }
+                }
+            }
             Rule::save => save = true,
+            Rule::set => {
+                for option in cmd.into_inner() {
+                    if let Err(e) = settings.set(option.as_str()) {
+                        infotext.push_str(&e);
+                    }
+                }
+            }
             Rule::gatherall => {
                 // When the command is still to be fully built
                 clear = false;
@@ -395,43 +1466,150 @@ fn main() -> Result<(), Error> {
             | Rule::backspace_char
             | Rule::cmd
             | Rule::gatherone
-            | Rule::cmd_list => (),
+            | Rule::setoption
+            | Rule::force
+            | Rule::filename
+            | Rule::append
+            | Rule::linenr
+            | Rule::range
+            | Rule::target
+            | Rule::bufnr
+            | Rule::window
+            | Rule::windowcmd
+            | Rule::visualcmd
+            | Rule::yank
+            | Rule::visual_list
+            | Rule::cmd_list
+            | Rule::subpattern
+            | Rule::subreplacement
+            | Rule::subflags
+            | Rule::confirmother
+            | Rule::valuetype
+            | Rule::hexpattern
+            | Rule::value
+            | Rule::confirm_list => (),
+        }
+
+        if let Some(split) = newsplit {
+            // The new window starts as a copy of the split one
+            windows.push(windows[active]);
+            layout.split(active, windows.len() - 1, split);
+            active = windows.len() - 1;
         }
+        if only {
+            windows = vec![windows[active]];
+            layout = Layout::default();
+            active = 0;
+        }
+
+        // The commands might have switched to another document
+        let win = &mut windows[active];
+        if current != win.doc {
+            win.show(&mut docs, current);
+        }
+        let doc = &mut docs[win.doc];
 
         if save {
-            if path.exists() {
-                let mut file = std::fs::OpenOptions::new()
-                    .read(true)
-                    .write(true)
-                    .create(true)
-                    .truncate(false)
-                    .open(path)
-                    .context("File could not be opened.")?;
-                file.seek(SeekFrom::Start(0))?;
-                file.write_all(&buf)?;
-                file.set_len(buf.len() as u64)?;
-                command.push_str("File saved!");
-            } else {
-                command.push_str("Careful, file could not be saved!");
-            }
-            // TODO: define filename during runtime
+            let saved = match &doc.path {
+                Some(path) => file::save(path, &mut doc.buf, &settings),
+                None => Err(anyhow!("E32: No file name")),
+            };
+            match saved {
+                Ok(()) => {
+                    doc.history.mark_saved();
+                    infotext.push_str("File saved!");
+                }
+                Err(e) => {
+                    infotext.push_str(&format!("Careful, file could not be saved! {}", e));
+                    // Do not lose the data when using ':wq'
+                    quitnow = false;
+                    closewindow = false;
+                }
+            }
         }
+        if let Some(name) = saveas {
+            match file::save_as(&name, &mut doc.buf) {
+                Ok(()) => {
+                    doc.path = Some(name);
+                    doc.history.mark_saved();
+                    infotext.push_str("File saved!");
+                }
+                Err(e) => infotext.push_str(&format!("Careful, file could not be saved! {}", e)),
+            }
+        }
+        // What went to the register '+' also goes to the system clipboard
+        if let Some(data) = registers.take_clipboard() {
+            let text = clipboard::format(&data, settings.clipformat);
+            match clipboard::copy(&text, &settings.clipboard) {
+                Ok(()) => infotext.push_str(&format!("{} bytes copied", data.len())),
+                Err(e) => infotext.push_str(&format!("Could not copy to the clipboard: {}", e)),
+            }
+        }
+        // The register is only used by the command following it
+        if clear && !keepregister {
+            register = None;
+        }
+        // The replacements confirmed one by one are undone together
+        let confirming = substitute.as_ref().is_some_and(|s| s.current().is_some());
         if clear {
             command.clear();
+            // The command is complete, so all its changes form one undo step
+            if !confirming {
+                doc.history.close(win.cursor);
+            }
         }
 
-        // Always move screen when cursor leaves screen
-        if cursor.pos() > ((screenheight + screenoffset).saturating_sub(1) * COLS).saturating_sub(1)
-        {
-            screenoffset = 2 + cursor.pos() / COLS - screenheight;
+        if closewindow {
+            // The document remembers where the closed window was
+            let win = windows[active];
+            docs[win.doc].cursor = win.cursor;
+            docs[win.doc].screenoffset = win.screenoffset;
+            windows.remove(active);
+            layout.close(active);
+            active = cmp::min(active, windows.len() - 1);
         }
-        if cursor.pos() < screenoffset * COLS {
-            screenoffset = cursor.pos() / COLS;
+
+        // While confirming a substitution, the cursor is on the match in question
+        if let Some(s) = &substitute {
+            match s.current() {
+                Some(range) => {
+                    windows[active].cursor.set_pos(range.start);
+                    let replacement = s.replacement.text();
+                    infotext.push_str(&format!("replace with {} (y/n/a/q/l)?", replacement));
+                }
+                None => {
+                    infotext.push_str(&s.summary());
+                    substitute = None;
+                }
+            }
+        }
+        if let (Some(v), true) = (visual, infotext.is_empty()) {
+            infotext.push_str(v.name());
         }
 
-        draw(&buf, COLS, &command, &infotext, cursor, screenoffset)?;
+        fit_windows(
+            &mut docs,
+            &mut windows,
+            &layout,
+            active,
+            &settings,
+            &mut cols,
+        );
+
+        let search = lastsearch.as_ref().filter(|_| hlsearch);
+        let views = get_views(
+            &docs,
+            &windows,
+            &layout,
+            active,
+            visual,
+            search.map(|s| &s.pattern),
+            cols,
+        );
+        draw(&views, cols, &command, &infotext)?;
     }
 
+    execute!(std::io::stdout(), DisableBracketedPaste)?;
     disable_raw_mode()?;
     Ok(())
 }
@@ -0,0 +1,91 @@
+// Lazily loaded view of a file.
+// Only the pages that are actually accessed get read from the file, a small
+// cache keeps the most recently loaded pages around. This allows opening
+// huge files and block devices without reading them completely.
+
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::io::SeekFrom;
+use std::rc::Rc;
+
+const PAGE_SIZE: usize = 1 << 16;
+// 16MB of cached pages
+const CACHED_PAGES: usize = 256;
+
+pub struct Pages {
+    file: RefCell<File>,
+    len: usize,
+    cache: RefCell<HashMap<usize, Rc<Vec<u8>>>>,
+    // Loading order of the cached pages, the oldest gets dropped first
+    loaded: RefCell<VecDeque<usize>>,
+    // Set when a page could not be read, E.g. as the file got truncated
+    failed: Cell<bool>,
+}
+
+impl Pages {
+    pub fn new(mut file: File) -> io::Result<Self> {
+        // Seeking also works for block devices, where the metadata has no length
+        let len = file.seek(SeekFrom::End(0))? as usize;
+        Ok(Pages {
+            file: RefCell::new(file),
+            len,
+            cache: RefCell::new(HashMap::new()),
+            loaded: RefCell::new(VecDeque::new()),
+            failed: Cell::new(false),
+        })
+    }
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    // Whether some data is unknown, saving it would write zeros instead
+    pub fn failed(&self) -> bool {
+        self.failed.get()
+    }
+    fn page(&self, index: usize) -> Rc<Vec<u8>> {
+        if let Some(page) = self.cache.borrow().get(&index) {
+            return page.clone();
+        }
+        let start = index * PAGE_SIZE;
+        let size = std::cmp::min(PAGE_SIZE, self.len - start);
+        // A page that can not be read is shown as zeros, as there is
+        // no way to show data we do not have.
+        let mut data = vec![0; size];
+        let mut file = self.file.borrow_mut();
+        let read = file
+            .seek(SeekFrom::Start(start as u64))
+            .and_then(|_| file.read_exact(&mut data));
+        if read.is_err() {
+            self.failed.set(true);
+        }
+        let page = Rc::new(data);
+        let mut cache = self.cache.borrow_mut();
+        let mut loaded = self.loaded.borrow_mut();
+        if loaded.len() >= CACHED_PAGES {
+            if let Some(oldest) = loaded.pop_front() {
+                cache.remove(&oldest);
+            }
+        }
+        cache.insert(index, page.clone());
+        loaded.push_back(index);
+        page
+    }
+    // Appends len bytes starting at pos to out
+    pub fn read_into(&self, pos: usize, len: usize, out: &mut Vec<u8>) {
+        let end = std::cmp::min(pos + len, self.len);
+        let mut pos = pos;
+        while pos < end {
+            let page = self.page(pos / PAGE_SIZE);
+            let offset = pos % PAGE_SIZE;
+            let amount = std::cmp::min(end - pos, page.len() - offset);
+            out.extend_from_slice(&page[offset..offset + amount]);
+            pos += amount;
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./pages_test.rs"]
+mod pages_test;
@@ -0,0 +1,59 @@
+use super::*;
+
+fn testfile(name: &str, data: &[u8]) -> File {
+    let path = std::env::temp_dir().join(format!("hexdino_pages_test_{}", name));
+    std::fs::write(&path, data).unwrap();
+    let file = File::open(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    file
+}
+
+#[test]
+fn pages_len() {
+    let pages = Pages::new(testfile("len", &[0x01, 0x02, 0x03])).unwrap();
+    assert_eq!(pages.len(), 3);
+}
+#[test]
+fn pages_read_into() {
+    let pages = Pages::new(testfile("read_into", &[0x01, 0x02, 0x03])).unwrap();
+    let mut out = Vec::new();
+    pages.read_into(1, 2, &mut out);
+    assert_eq!(out, vec![0x02, 0x03]);
+}
+#[test]
+fn pages_read_into_out_of_range() {
+    let pages = Pages::new(testfile("out_of_range", &[0x01, 0x02, 0x03])).unwrap();
+    let mut out = Vec::new();
+    pages.read_into(2, 10, &mut out);
+    assert_eq!(out, vec![0x03]);
+}
+#[test]
+fn pages_read_across_pages() {
+    let data: Vec<u8> = (0..3 * PAGE_SIZE).map(|i| i as u8).collect();
+    let pages = Pages::new(testfile("across", &data)).unwrap();
+    let mut out = Vec::new();
+    pages.read_into(PAGE_SIZE - 2, PAGE_SIZE + 4, &mut out);
+    assert_eq!(out, data[PAGE_SIZE - 2..2 * PAGE_SIZE + 2]);
+}
+#[test]
+fn pages_load_lazily() {
+    let data: Vec<u8> = (0..3 * PAGE_SIZE).map(|i| i as u8).collect();
+    let pages = Pages::new(testfile("lazily", &data)).unwrap();
+    let mut out = Vec::new();
+    pages.read_into(PAGE_SIZE + 1, 1, &mut out);
+    assert_eq!(pages.cache.borrow().len(), 1);
+    assert!(pages.cache.borrow().contains_key(&1));
+}
+#[test]
+fn pages_read_truncated_file() {
+    let path = std::env::temp_dir().join("hexdino_pages_test_truncated");
+    std::fs::write(&path, [0x01, 0x02, 0x03]).unwrap();
+    let pages = Pages::new(File::open(&path).unwrap()).unwrap();
+    std::fs::write(&path, []).unwrap();
+    let mut out = Vec::new();
+    pages.read_into(0, 3, &mut out);
+    // The data is unknown, so it shows as zeros
+    assert_eq!(out, vec![0x00, 0x00, 0x00]);
+    assert!(pages.failed());
+    std::fs::remove_file(&path).unwrap();
+}
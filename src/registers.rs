@@ -0,0 +1,94 @@
+// Registers for yank, delete and put, like the ones in vim.
+// The unnamed register '"' always holds the last yanked or deleted bytes,
+// '0' the last yanked ones. The named registers 'a' to 'z' are only filled
+// when asked for, using the upper case name appends to them.
+// What is stored into '+' is also meant for the system clipboard.
+
+use std::collections::BTreeMap;
+
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Register {
+    pub data: Vec<u8>,
+    // Whole lines were yanked, they are put as whole lines again
+    pub lines: bool,
+}
+
+// Putting more at once is refused, as it all needs to be in memory
+const MAX_PUT: usize = 1 << 30;
+
+impl Register {
+    // The data repeated count times, None when that gets too big
+    pub fn repeated(&self, count: usize) -> Option<Vec<u8>> {
+        let len = self.data.len().checked_mul(count)?;
+        (len <= MAX_PUT).then(|| self.data.repeat(count))
+    }
+}
+
+#[derive(Default)]
+pub struct Registers {
+    registers: BTreeMap<char, Register>,
+    // Stored to '+', but not yet copied to the system clipboard
+    clipboard: Option<Vec<u8>>,
+}
+
+impl Registers {
+    // Stores what was yanked or deleted, into the register named by the
+    // user, or else the unnamed one
+    fn store(&mut self, name: Option<char>, register: Register) {
+        let register = match name {
+            Some(name) if name.is_ascii_uppercase() => {
+                let named = self.registers.entry(name.to_ascii_lowercase()).or_default();
+                named.data.extend_from_slice(&register.data);
+                named.lines |= register.lines;
+                named.clone()
+            }
+            Some('+') => {
+                self.clipboard = Some(register.data.clone());
+                self.registers.insert('+', register.clone());
+                register
+            }
+            Some(name) if name != '"' => {
+                self.registers.insert(name, register.clone());
+                register
+            }
+            _ => register,
+        };
+        self.registers.insert('"', register);
+    }
+    pub fn yanked(&mut self, name: Option<char>, data: Vec<u8>, lines: bool) {
+        let register = Register { data, lines };
+        if name.is_none() {
+            self.registers.insert('0', register.clone());
+        }
+        self.store(name, register);
+    }
+    pub fn deleted(&mut self, name: Option<char>, data: Vec<u8>, lines: bool) {
+        if !data.is_empty() {
+            self.store(name, Register { data, lines });
+        }
+    }
+    // Text pasted in normal mode goes to the unnamed register
+    pub fn pasted(&mut self, data: Vec<u8>) {
+        self.store(None, Register { data, lines: false });
+    }
+    // Bytes to copy to the system clipboard, see src/clipboard.rs
+    pub fn take_clipboard(&mut self) -> Option<Vec<u8>> {
+        self.clipboard.take()
+    }
+    // Without a name, the unnamed register is used
+    pub fn get(&self, name: Option<char>) -> Option<&Register> {
+        let name = name.unwrap_or('"').to_ascii_lowercase();
+        self.registers.get(&name).filter(|r| !r.data.is_empty())
+    }
+    // All registers with content, the unnamed one first
+    pub fn list(&self) -> Vec<(char, &Register)> {
+        let mut list: Vec<(char, &Register)> =
+            self.registers.iter().map(|(c, r)| (*c, r)).collect();
+        list.sort_by_key(|(c, _)| *c != '"');
+        list
+    }
+}
+
+#[cfg(test)]
+#[path = "./registers_test.rs"]
+mod registers_test;
@@ -0,0 +1,72 @@
+use super::*;
+
+#[test]
+fn registers_yank_fills_unnamed_and_zero() {
+    let mut registers = Registers::default();
+    registers.yanked(None, vec![1, 2], false);
+    assert_eq!(registers.get(None).unwrap().data, vec![1, 2]);
+    assert_eq!(registers.get(Some('0')).unwrap().data, vec![1, 2]);
+}
+#[test]
+fn registers_delete_keeps_zero() {
+    let mut registers = Registers::default();
+    registers.yanked(None, vec![1, 2], false);
+    registers.deleted(None, vec![3], false);
+    assert_eq!(registers.get(None).unwrap().data, vec![3]);
+    assert_eq!(registers.get(Some('0')).unwrap().data, vec![1, 2]);
+}
+#[test]
+fn registers_named() {
+    let mut registers = Registers::default();
+    registers.yanked(Some('a'), vec![1], false);
+    registers.deleted(None, vec![2], false);
+    assert_eq!(registers.get(Some('a')).unwrap().data, vec![1]);
+    assert_eq!(registers.get(None).unwrap().data, vec![2]);
+    assert!(registers.get(Some('0')).is_none());
+    assert!(registers.get(Some('b')).is_none());
+}
+#[test]
+fn registers_upper_case_appends() {
+    let mut registers = Registers::default();
+    registers.yanked(Some('a'), vec![1], false);
+    registers.yanked(Some('A'), vec![2, 3], true);
+    let register = registers.get(Some('a')).unwrap();
+    assert_eq!(register.data, vec![1, 2, 3]);
+    assert!(register.lines);
+    assert_eq!(registers.get(None).unwrap().data, vec![1, 2, 3]);
+}
+#[test]
+fn registers_empty_delete_is_ignored() {
+    let mut registers = Registers::default();
+    registers.yanked(None, vec![1], false);
+    registers.deleted(None, Vec::new(), false);
+    assert_eq!(registers.get(None).unwrap().data, vec![1]);
+}
+#[test]
+fn registers_list_unnamed_first() {
+    let mut registers = Registers::default();
+    registers.yanked(Some('b'), vec![1], false);
+    registers.yanked(None, vec![2], false);
+    let names: Vec<char> = registers.list().iter().map(|(c, _)| *c).collect();
+    assert_eq!(names, vec!['"', '0', 'b']);
+}
+#[test]
+fn registers_clipboard() {
+    let mut registers = Registers::default();
+    registers.yanked(Some('a'), vec![1], false);
+    assert_eq!(registers.take_clipboard(), None);
+    registers.yanked(Some('+'), vec![2], false);
+    assert_eq!(registers.take_clipboard(), Some(vec![2]));
+    assert_eq!(registers.take_clipboard(), None);
+    assert_eq!(registers.get(Some('+')).unwrap().data, vec![2]);
+}
+#[test]
+fn registers_repeated() {
+    let register = Register {
+        data: vec![0x01, 0x02],
+        lines: false,
+    };
+    assert_eq!(register.repeated(2), Some(vec![0x01, 0x02, 0x01, 0x02]));
+    assert_eq!(register.repeated(usize::MAX), None);
+    assert_eq!(register.repeated(MAX_PUT), None);
+}
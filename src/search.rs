@@ -5,78 +5,430 @@
 // If it is not divisible by two, then the behaviour should
 // be like if there would be a wildcard appended at the end.
 // Example: Searching for 1F1 makes the needle kind of 1F1X
+// Signatures can also contain:
+// - Spaces, which are ignored, and '?' as wildcard
+// - A mask for the byte before, "4?&F0" or "40&F0" match 0x40 to 0x4F
+// - Gaps of any bytes, "E8 [4] 48" skips four bytes, "[2-8]" two to eight
+//   and "[2-]" two or more, up to 4096
+// - Alternatives, "(AA|BB CC) DD"
+// They are turned into a regular expression, which finds them in linear time.
 
-pub trait Search {
-    fn search(&self, needle: &[u8]) -> Option<usize>;
+use super::buffer::{Buffer, CHUNK};
+use super::encoding::Encoding;
+use regex::bytes::{Regex, RegexBuilder};
+use regex_automata::hybrid::dfa::{OverlappingState, DFA};
+use regex_automata::nfa::thompson;
+use regex_automata::util::syntax;
+use regex_automata::{Input, MatchKind};
+use std::cmp;
+use std::ops::Range;
+
+// The regular expression matching one byte, of which only the bits in mask matter
+fn byte_class(value: u8, mask: u8) -> String {
+    match mask {
+        0xFF => format!("\\x{:02x}", value),
+        0x00 => String::from("(?s:.)"),
+        _ => {
+            let bytes: Vec<u8> = (0..=255).filter(|b| b & mask == value & mask).collect();
+            let mut class = String::from("[");
+            // Runs of following bytes are written as range
+            for run in bytes.chunk_by(|a, b| a + 1 == *b) {
+                let (first, last) = (run[0], run[run.len() - 1]);
+                class.push_str(&format!("\\x{:02x}-\\x{:02x}", first, last));
+            }
+            class.push(']');
+            class
+        }
+    }
 }
 
-impl Search for Vec<u8> {
-    fn search(&self, search: &[u8]) -> Option<usize> {
-        let mut needle = Vec::new();
-        for byte in search {
-            let nibble = match byte {
-                c @ 48..=57 => c - 48, // Numbers from 0 to 9
-                c @ b'a'..=b'f' => c - 87,
-                c @ b'A'..=b'F' => c - 55,
-                _ => 0x10, // All other elements are wildcards
-            };
-            needle.push(nibble);
+// A regular expression on bytes. Going backwards, a reverse DFA finds the
+// last match in one pass, instead of searching forward from every position.
+#[derive(Clone, Debug)]
+pub struct Expression {
+    regex: Regex,
+    reverse: DFA,
+}
+
+impl Expression {
+    // Without unicode, "\xff" is the byte 0xFF and "." any byte
+    fn new(expression: &str, ignorecase: bool) -> Option<Self> {
+        let regex = RegexBuilder::new(expression)
+            .unicode(false)
+            .case_insensitive(ignorecase)
+            .build()
+            .ok()?;
+        let reverse = DFA::builder()
+            // Every position a match starts at is reported
+            .configure(DFA::config().match_kind(MatchKind::All))
+            .syntax(
+                syntax::Config::new()
+                    .unicode(false)
+                    .utf8(false)
+                    .case_insensitive(ignorecase),
+            )
+            .thompson(thompson::Config::new().reverse(true))
+            .build(expression)
+            .ok()?;
+        Some(Expression { regex, reverse })
+    }
+    // The first match starting at from or later
+    fn find(&self, haystack: &[u8], from: usize) -> Option<Range<usize>> {
+        self.regex.find_at(haystack, from).map(|m| m.range())
+    }
+    // The last match starting within the first starts bytes
+    fn rfind(&self, haystack: &[u8], starts: usize) -> Option<usize> {
+        let mut cache = self.reverse.create_cache();
+        let input = Input::new(haystack);
+        let mut state = OverlappingState::start();
+        loop {
+            let searched = self
+                .reverse
+                .try_search_overlapping_rev(&mut cache, &input, &mut state);
+            // The lazy DFA only gives up in rare cases, then search forward
+            if searched.is_err() {
+                return self.rfind_forward(haystack, starts);
+            }
+            match state.get_match() {
+                Some(found) if found.offset() < starts => return Some(found.offset()),
+                Some(_) => continue,
+                None => return None,
+            }
+        }
+    }
+    fn rfind_forward(&self, haystack: &[u8], starts: usize) -> Option<usize> {
+        let mut last = None;
+        let mut from = 0;
+        while from < starts {
+            match self.find(haystack, from) {
+                Some(found) if found.start < starts => {
+                    last = Some(found.start);
+                    from = found.start + 1;
+                }
+                _ => break,
+            }
         }
+        last
+    }
+}
 
-        if needle.len() > 2 * self.len() {
+// A hex signature, see above
+#[derive(Clone, Debug)]
+pub struct Signature {
+    expression: Expression,
+    // Longest possible match
+    len: usize,
+}
+
+impl Signature {
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut expression = String::new();
+        // Nibbles of the byte being typed, None is a wildcard
+        let mut nibbles: Vec<Option<u8>> = Vec::new();
+        // The last byte as value and mask, it may still get a mask with '&'
+        let mut byte: Option<(u8, u8)> = None;
+        // Longest match of every open group: of its alternatives and of the current one
+        let mut groups: Vec<(usize, usize)> = vec![(0, 0)];
+        let mut chars = text.chars();
+        loop {
+            let c = chars.next();
+            if c == Some(' ') {
+                continue;
+            }
+            let nibble = c.filter(|c| !"&[]()|".contains(*c));
+            if let Some(n) = nibble {
+                nibbles.push(n.to_digit(16).map(|n| n as u8));
+            }
+            // At the end, a single wildcard nibble makes no byte at all
+            if c.is_none() && nibbles == [None] {
+                nibbles.clear();
+            }
+            // A byte is complete with two nibbles, or when something else
+            // follows a single one, which then gets a wildcard appended
+            if nibbles.len() == 2 || (nibble.is_none() && nibbles.len() == 1) {
+                nibbles.resize(2, None);
+                let value = nibbles[0].unwrap_or(0) << 4 | nibbles[1].unwrap_or(0);
+                let mask = nibbles[0].map_or(0, |_| 0xF0) | nibbles[1].map_or(0, |_| 0x0F);
+                if let Some((value, mask)) = byte.replace((value, mask)) {
+                    expression.push_str(&byte_class(value, mask));
+                }
+                groups.last_mut()?.1 += 1;
+                nibbles.clear();
+            }
+            if nibble.is_some() {
+                continue;
+            }
+            if c == Some('&') {
+                let mask: String = chars.by_ref().take(2).collect();
+                let mask = u8::from_str_radix(&mask, 16).ok()?;
+                let (value, oldmask) = byte.take()?;
+                byte = Some((value, oldmask & mask));
+                continue;
+            }
+            if let Some((value, mask)) = byte.take() {
+                expression.push_str(&byte_class(value, mask));
+            }
+            match c {
+                None => break,
+                Some('[') => {
+                    let mut gap = String::new();
+                    loop {
+                        match chars.next()? {
+                            ']' => break,
+                            c => gap.push(c),
+                        }
+                    }
+                    // Longer gaps would not be found across the chunks searched in
+                    let length = |text: &str| {
+                        let len = text.trim().parse::<usize>().ok()?;
+                        (len <= REGEX_OVERLAP).then_some(len)
+                    };
+                    let (min, max) = match gap.split_once('-') {
+                        Some((min, "")) => (length(min)?, None),
+                        Some((min, max)) => (length(min)?, Some(length(max)?)),
+                        None => (length(&gap)?, Some(length(&gap)?)),
+                    };
+                    // An open gap ends where the chunks stop overlapping too,
+                    // so that a match never depends on where a chunk starts
+                    let max = max.unwrap_or(REGEX_OVERLAP);
+                    if max == min {
+                        expression.push_str(&format!("(?s:.){{{}}}", min));
+                    } else {
+                        expression.push_str(&format!("(?s:.){{{},{}}}", min, max));
+                    }
+                    groups.last_mut()?.1 += max;
+                }
+                Some('(') => {
+                    expression.push_str("(?:");
+                    groups.push((0, 0));
+                }
+                Some('|') => {
+                    expression.push('|');
+                    let group = groups.last_mut()?;
+                    *group = (cmp::max(group.0, group.1), 0);
+                }
+                Some(')') => {
+                    expression.push(')');
+                    let (longest, current) = groups.pop()?;
+                    groups.last_mut()?.1 += cmp::max(longest, current);
+                }
+                _ => return None,
+            }
+        }
+        let (longest, current) = match groups[..] {
+            [group] => group,
+            _ => return None,
+        };
+        let len = cmp::max(longest, current);
+        if len == 0 {
             return None;
         }
+        let expression = Expression::new(&expression, false)?;
+        Some(Signature { expression, len })
+    }
+    // How much the chunks searched in need to overlap
+    fn overlap(&self) -> usize {
+        cmp::min(self.len, REGEX_OVERLAP).saturating_sub(1)
+    }
+}
+
+// Regex matches are found up to this length, even when crossing the
+// border of the chunks the data is searched in
+const REGEX_OVERLAP: usize = 4096;
+
+// How the text typed after '/' is searched
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+pub struct TextOptions {
+    pub encoding: Encoding,
+    // Only for ASCII letters
+    pub ignorecase: bool,
+}
 
-        let needle_bytes_odd: bool = needle.len() % 2 == 1;
-        let mut bytes_needed_to_match = needle.len() / 2;
-        if needle_bytes_odd {
-            bytes_needed_to_match += 1; // As we add the wildcard to make it even again
-            if *needle.last().unwrap_or(&0) >= 0x10 {
-                // This fixes the rare case when a user:
-                // - uses an odd amount of nibbles to search
-                // - ends his 3 nibbles with a wildcard character
-                // - wants the 2 nibbles to match on the last character of the haystack
-                // See: search_odd_at_end_left_over_range_x()
-                bytes_needed_to_match -= 1;
+impl TextOptions {
+    // Flags in front of the text change the options for one search:
+    // "\c" ignores the case, "\C" does not and "\u16le " sets the encoding
+    pub fn strip_flags(mut self, mut text: &str) -> (Self, &str) {
+        loop {
+            let flag = text.strip_prefix('\\').and_then(|t| t.split_once(' '));
+            if let Some((name, rest)) = flag.filter(|(name, _)| Encoding::from_name(name).is_some())
+            {
+                self.encoding = Encoding::from_name(name).unwrap_or_default();
+                text = rest;
+            } else if let Some(rest) = text.strip_prefix("\\c") {
+                self.ignorecase = true;
+                text = rest;
+            } else if let Some(rest) = text.strip_prefix("\\C") {
+                self.ignorecase = false;
+                text = rest;
+            } else {
+                return (self, text);
             }
         }
+    }
+}
 
-        // Search in h (haystack index) for n (needle index)
-        for h in 0..=self.len().saturating_sub(bytes_needed_to_match) {
-            for n in 0..bytes_needed_to_match {
-                // println!("h={}, n={}", h, n);
-                // needle_left_nibble
-                let nl = needle[2 * n];
-                // needle_right_nibble
-                let mut nr = 0x10; // Use the wildcard if it goes out of range
-                if 2 * n + 1 < needle.len() {
-                    // Ensuring no out of range access
-                    nr = needle[2 * n + 1];
-                }
-
-                // haystack_left_nibble
-                let hl = self[h + n] >> 4;
-                // haystack_right_nibble
-                let hr = self[h + n] % 16;
+// What was searched for
+#[derive(Clone, Debug)]
+pub enum Pattern {
+    // On bytes, E.g. "\x7fELF" or "[\x00-\x1f]{8,}", and the text as typed
+    Regex(Expression, String),
+    // A hex signature, and the text as typed
+    Hex(Signature, String),
+}
 
-                // When left nibble does not match and it is not a wildcard
-                if nl != hl && nl < 0x10 {
-                    break; // skip as left nibble is not a wildcard and it does not match
+impl Pattern {
+    // In UTF-8 the text is a regular expression. In other encodings the
+    // characters are searched for literally, as they are encoded.
+    pub fn regex(text: &str, options: TextOptions) -> Result<Self, String> {
+        let expression = match options.encoding {
+            Encoding::Utf8 => String::from(text),
+            encoding => {
+                let mut expression = String::new();
+                for c in text.chars() {
+                    let mut variants = vec![c];
+                    if options.ignorecase {
+                        variants = vec![c.to_ascii_lowercase(), c.to_ascii_uppercase()];
+                    }
+                    let mut alternatives = Vec::new();
+                    for variant in variants {
+                        let bytes = encoding
+                            .encode_char(variant)
+                            .ok_or_else(|| format!("Can not encode: {}", text))?;
+                        let escaped: Vec<String> =
+                            bytes.iter().map(|b| format!("\\x{:02x}", b)).collect();
+                        alternatives.push(escaped.concat());
+                    }
+                    expression.push_str(&format!("(?:{})", alternatives.join("|")));
                 }
-                if nr != hr && nr < 0x10 {
-                    break; // skip as right nibble is not a wildcard and it does not match
+                expression
+            }
+        };
+        let ignorecase = options.ignorecase && options.encoding == Encoding::Utf8;
+        let expression = Expression::new(&expression, ignorecase)
+            .ok_or_else(|| format!("Invalid pattern: {}", text))?;
+        Ok(Pattern::Regex(expression, String::from(text)))
+    }
+    pub fn hex(text: &str) -> Result<Self, String> {
+        let signature =
+            Signature::parse(text).ok_or_else(|| format!("Invalid pattern: {}", text))?;
+        Ok(Pattern::Hex(signature, String::from(text)))
+    }
+    // The text as typed by the user
+    pub fn text(&self) -> &str {
+        match self {
+            Pattern::Regex(_, text) | Pattern::Hex(_, text) => text,
+        }
+    }
+    // How much the chunks searched in need to overlap
+    fn overlap(&self) -> usize {
+        match self {
+            Pattern::Regex(..) => REGEX_OVERLAP,
+            Pattern::Hex(signature, _) => signature.overlap(),
+        }
+    }
+    fn expression(&self) -> &Expression {
+        match self {
+            Pattern::Regex(expression, _) => expression,
+            Pattern::Hex(signature, _) => &signature.expression,
+        }
+    }
+    // The first match starting at from or later
+    fn find(&self, haystack: &[u8], from: usize) -> Option<Range<usize>> {
+        self.expression().find(haystack, from)
+    }
+    // The last match starting within the first starts bytes
+    fn rfind(&self, haystack: &[u8], starts: usize) -> Option<usize> {
+        self.expression().rfind(haystack, starts)
+    }
+    // All matches overlapping the range, E.g. the bytes shown on screen.
+    // They do not overlap each other.
+    pub fn matches_in(&self, buf: &Buffer, range: Range<usize>) -> Vec<Range<usize>> {
+        let first = range.start.saturating_sub(self.overlap());
+        self.matches_from(buf, first, range)
+    }
+    // All matches completely within the range
+    pub fn matches_within(&self, buf: &Buffer, range: Range<usize>) -> Vec<Range<usize>> {
+        let mut matches = self.matches_from(buf, range.start, range.clone());
+        matches.retain(|m| m.end <= range.end);
+        matches
+    }
+    // How many matches there are in the whole data, and where the first
+    // few of them start, without keeping every match in memory
+    pub fn count_matches(&self, buf: &Buffer, keep: usize) -> (usize, Vec<usize>) {
+        let mut count = 0;
+        let mut starts = Vec::new();
+        self.each_match(buf, 0, 0..buf.len(), |m| {
+            if count < keep {
+                starts.push(m.start);
+            }
+            count += 1;
+        });
+        (count, starts)
+    }
+    // Matches overlapping the range, searched from the position first on
+    fn matches_from(&self, buf: &Buffer, first: usize, range: Range<usize>) -> Vec<Range<usize>> {
+        let mut matches = Vec::new();
+        self.each_match(buf, first, range, |m| matches.push(m));
+        matches
+    }
+    // Calls found_at for each match overlapping the range, in order
+    fn each_match<F>(&self, buf: &Buffer, first: usize, range: Range<usize>, mut found_at: F)
+    where
+        F: FnMut(Range<usize>),
+    {
+        let overlap = self.overlap();
+        // Where the next match may start, the data is read in chunks
+        let mut pos = first;
+        while pos < range.end {
+            let stop = cmp::min(pos + CHUNK, range.end);
+            let end = cmp::min(stop + overlap, buf.len());
+            let data = buf.read(pos..cmp::max(pos, end));
+            let mut from = 0;
+            while let Some(found) = self.find(&data, from) {
+                if pos + found.start >= stop {
+                    break;
+                }
+                if pos + found.end > range.start {
+                    found_at(pos + found.start..pos + found.end);
                 }
-                // when all elements of n match in h
-                if n + 1 == bytes_needed_to_match {
-                    // return position of h
-                    return Some(h);
+                from = cmp::max(found.end, found.start + 1);
+                if from > data.len() {
+                    break;
                 }
             }
+            pos = cmp::max(stop, pos + from);
+        }
+    }
+    // The next match after pos, or before it when going backward.
+    // At the end of the data the search continues at the other end,
+    // which is told by the returned bool.
+    pub fn find_next(&self, buf: &Buffer, pos: usize, backward: bool) -> Option<(usize, bool)> {
+        let overlap = self.overlap();
+        if backward {
+            let rfind = |chunk: &[u8], starts| self.rfind(chunk, starts);
+            match buf.rfind_in_chunks(pos, overlap, rfind) {
+                Some(found) => Some((found, false)),
+                None => buf
+                    .rfind_in_chunks(buf.len(), overlap, rfind)
+                    .map(|f| (f, true)),
+            }
+        } else {
+            let find = |chunk: &[u8]| self.find(chunk, 0).map(|found| found.start);
+            match buf.find_in_chunks_from(pos + 1, overlap, find) {
+                Some(found) => Some((found, false)),
+                None => buf.find_in_chunks(overlap, find).map(|f| (f, true)),
+            }
         }
-        None
     }
 }
 
+// The last search, n repeats it in the same direction and N in the other one
+#[derive(Clone, Debug)]
+pub struct LastSearch {
+    pub pattern: Pattern,
+    pub backward: bool,
+}
+
 #[cfg(test)]
 #[path = "./search_test.rs"]
 mod cursor_test;
@@ -1,158 +1,391 @@
 use super::*;
 
+// Start of the first match of the hex signature
+fn search(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    let pattern = Pattern::hex(&String::from_utf8_lossy(needle)).ok()?;
+    let buf = Buffer::from(haystack.to_vec());
+    let matches = pattern.matches_in(&buf, 0..buf.len());
+    matches.first().map(|m| m.start)
+}
+
 #[test]
 fn search_partial_at_start() {
-    let buf = vec![0x01, 0x02, 0x03, 0x04, 0x05];
+    let buf = [0x01, 0x02, 0x03, 0x04, 0x05];
     let sub = "01".as_bytes();
-    assert_eq!(buf.search(&sub), Some(0));
+    assert_eq!(search(&buf, sub), Some(0));
 }
 #[test]
 fn search_partial_at_middle() {
-    let buf = vec![0x01, 0x02, 0x03, 0x04, 0x05];
+    let buf = [0x01, 0x02, 0x03, 0x04, 0x05];
     let sub = "0304".as_bytes();
-    assert_eq!(buf.search(&sub), Some(2));
+    assert_eq!(search(&buf, sub), Some(2));
 }
 #[test]
 fn search_partial_at_end() {
-    let buf = vec![0x01, 0x02, 0x03, 0x04, 0x05];
+    let buf = [0x01, 0x02, 0x03, 0x04, 0x05];
     let sub = "0405".as_bytes();
-    assert_eq!(buf.search(&sub), Some(3));
+    assert_eq!(search(&buf, sub), Some(3));
 }
 #[test]
 fn search_partial_after_end() {
-    let buf = vec![0x01, 0x02, 0x03, 0x04, 0x05];
+    let buf = [0x01, 0x02, 0x03, 0x04, 0x05];
     let sub = "0506".as_bytes();
-    assert_eq!(buf.search(&sub), None);
+    assert_eq!(search(&buf, sub), None);
 }
 #[test]
 fn search_partial_before_start() {
-    let buf = vec![0x01, 0x02, 0x03, 0x04, 0x05];
+    let buf = [0x01, 0x02, 0x03, 0x04, 0x05];
     let sub = "0001".as_bytes();
-    assert_eq!(buf.search(&sub), None);
+    assert_eq!(search(&buf, sub), None);
 }
 #[test]
 fn search_short() {
-    let buf = vec![0x01, 0x02, 0x03, 0x04, 0x05];
+    let buf = [0x01, 0x02, 0x03, 0x04, 0x05];
     let sub = "02".as_bytes();
-    assert_eq!(buf.search(&sub), Some(1));
+    assert_eq!(search(&buf, sub), Some(1));
 }
 #[test]
 fn search_too_long() {
-    let buf = vec![0x01, 0x02, 0x03, 0x04, 0x05];
+    let buf = [0x01, 0x02, 0x03, 0x04, 0x05];
     let sub = "010203040506".as_bytes();
-    assert_eq!(buf.search(&sub), None);
+    assert_eq!(search(&buf, sub), None);
 }
 #[test]
 fn search_full() {
-    let buf = vec![0x01, 0x02, 0x03, 0x04, 0x05];
+    let buf = [0x01, 0x02, 0x03, 0x04, 0x05];
     let sub = "0102030405".as_bytes();
-    assert_eq!(buf.search(&sub), Some(0));
+    assert_eq!(search(&buf, sub), Some(0));
 }
 #[test]
 fn search_swapped() {
-    let buf = vec![0x01, 0x02, 0x03, 0x04, 0x05];
+    let buf = [0x01, 0x02, 0x03, 0x04, 0x05];
     let sub = "0504030201".as_bytes();
-    assert_eq!(buf.search(&sub), None);
+    assert_eq!(search(&buf, sub), None);
 }
 #[test]
 fn search_higher_than_9() {
-    let buf = vec![0x0A, 0x0C, 0x0D, 0x0E, 0x0F];
+    let buf = [0x0A, 0x0C, 0x0D, 0x0E, 0x0F];
     let sub = "0C0D0E".as_bytes();
-    assert_eq!(buf.search(&sub), Some(1));
+    assert_eq!(search(&buf, sub), Some(1));
 }
 #[test]
 fn search_higher_than_f() {
-    let buf = vec![0x0A, 0x3C, 0x1D, 0xEE, 0x0F];
+    let buf = [0x0A, 0x3C, 0x1D, 0xEE, 0x0F];
     let sub = "3C1DEE".as_bytes();
-    assert_eq!(buf.search(&sub), Some(1));
+    assert_eq!(search(&buf, sub), Some(1));
 }
 #[test]
 fn search_with_single_wildcard_0x10() {
-    let buf = vec![0x0A, 0x3C, 0x1D, 0xEE, 0x0F];
+    let buf = [0x0A, 0x3C, 0x1D, 0xEE, 0x0F];
     let sub = "3C1\x10EE".as_bytes();
-    assert_eq!(buf.search(&sub), Some(1));
+    assert_eq!(search(&buf, sub), Some(1));
 }
 #[test]
 fn search_with_wildcard_0x7f() {
-    let buf = vec![0x0A, 0x3C, 0x1D, 0xEE, 0x0F];
+    let buf = [0x0A, 0x3C, 0x1D, 0xEE, 0x0F];
     let sub = "3C\x7f\x7fEE".as_bytes();
-    assert_eq!(buf.search(&sub), Some(1));
+    assert_eq!(search(&buf, sub), Some(1));
 }
 #[test]
 fn search_with_wildcard_x_big_x() {
-    let buf = vec![0x0A, 0x3C, 0x1D, 0xEE, 0x0F];
+    let buf = [0x0A, 0x3C, 0x1D, 0xEE, 0x0F];
     let sub = "3CxXEE".as_bytes();
-    assert_eq!(buf.search(&sub), Some(1));
+    assert_eq!(search(&buf, sub), Some(1));
 }
 #[test]
 fn search_with_wildcards() {
-    let buf = vec![0x0A, 0x3C, 0x1D, 0xEE, 0x0F];
+    let buf = [0x0A, 0x3C, 0x1D, 0xEE, 0x0F];
     let sub = "xxxxxx".as_bytes();
-    assert_eq!(buf.search(&sub), Some(0));
+    assert_eq!(search(&buf, sub), Some(0));
 }
 #[test]
 fn search_shifted() {
-    let buf = vec![0x0A, 0x3C, 0x1D, 0xEE, 0x0F];
+    let buf = [0x0A, 0x3C, 0x1D, 0xEE, 0x0F];
     let sub = "A3".as_bytes();
-    assert_eq!(buf.search(&sub), None);
+    assert_eq!(search(&buf, sub), None);
 }
 #[test]
 fn search_odd_at_start_left() {
-    let buf = vec![0x01, 0x02, 0x03, 0x04, 0x05];
+    let buf = [0x01, 0x02, 0x03, 0x04, 0x05];
     let sub = "010".as_bytes();
-    assert_eq!(buf.search(&sub), Some(0));
+    assert_eq!(search(&buf, sub), Some(0));
 }
 #[test]
 fn search_odd_at_middle_left() {
-    let buf = vec![0x01, 0x02, 0x03, 0x04, 0x05];
+    let buf = [0x01, 0x02, 0x03, 0x04, 0x05];
     let sub = "030".as_bytes();
-    assert_eq!(buf.search(&sub), Some(2));
+    assert_eq!(search(&buf, sub), Some(2));
 }
 #[test]
 fn search_odd_at_end_left() {
-    let buf = vec![0x01, 0x02, 0x03, 0x04, 0x05];
+    let buf = [0x01, 0x02, 0x03, 0x04, 0x05];
     let sub = "040".as_bytes();
-    assert_eq!(buf.search(&sub), Some(3));
+    assert_eq!(search(&buf, sub), Some(3));
 }
 #[test]
 fn search_odd_at_end_left_1() {
-    let buf = vec![0x01, 0x02, 0x03, 0x04, 0x05];
+    let buf = [0x01, 0x02, 0x03, 0x04, 0x05];
     let sub = "041".as_bytes();
-    assert_eq!(buf.search(&sub), None);
+    assert_eq!(search(&buf, sub), None);
 }
 #[test]
 fn search_odd_at_start_right() {
-    let buf = vec![0x01, 0x02, 0x03, 0x04, 0x05];
+    let buf = [0x01, 0x02, 0x03, 0x04, 0x05];
     let sub = "102".as_bytes();
-    assert_eq!(buf.search(&sub), None);
+    assert_eq!(search(&buf, sub), None);
 }
 #[test]
 fn search_odd_at_middle_right() {
-    let buf = vec![0x01, 0x02, 0x03, 0x04, 0x05];
+    let buf = [0x01, 0x02, 0x03, 0x04, 0x05];
     let sub = "203".as_bytes();
-    assert_eq!(buf.search(&sub), None);
+    assert_eq!(search(&buf, sub), None);
 }
 #[test]
 fn search_odd_at_end_right() {
-    let buf = vec![0x01, 0x02, 0x03, 0x04, 0x05];
+    let buf = [0x01, 0x02, 0x03, 0x04, 0x05];
     let sub = "304".as_bytes();
-    assert_eq!(buf.search(&sub), None);
+    assert_eq!(search(&buf, sub), None);
 }
 #[test]
 fn search_odd_at_end_left_over_range_0() {
-    let buf = vec![0x01, 0x02, 0x03, 0x04, 0x05];
+    let buf = [0x01, 0x02, 0x03, 0x04, 0x05];
     let sub = "050".as_bytes();
-    assert_eq!(buf.search(&sub), None);
+    assert_eq!(search(&buf, sub), None);
 }
 #[test]
 fn search_odd_at_end_left_over_range_1() {
-    let buf = vec![0x01, 0x02, 0x03, 0x04, 0x05];
+    let buf = [0x01, 0x02, 0x03, 0x04, 0x05];
     let sub = "051".as_bytes();
-    assert_eq!(buf.search(&sub), None);
+    assert_eq!(search(&buf, sub), None);
 }
 #[test]
 fn search_odd_at_end_left_over_range_x() {
-    let buf = vec![0x01, 0x02, 0x03, 0x04, 0x05];
+    let buf = [0x01, 0x02, 0x03, 0x04, 0x05];
     let sub = "05X".as_bytes();
-    assert_eq!(buf.search(&sub), Some(4));
+    assert_eq!(search(&buf, sub), Some(4));
+}
+#[test]
+fn search_odd_x_on_single_byte() {
+    let buf = [0x05];
+    let sub = "05X".as_bytes();
+    assert_eq!(search(&buf, sub), Some(0));
+}
+#[test]
+fn search_pattern_next_forward() {
+    let buf = Buffer::from(b"ab ab ab".to_vec());
+    let pattern = Pattern::regex("ab", TextOptions::default()).unwrap();
+    assert_eq!(pattern.find_next(&buf, 0, false), Some((3, false)));
+    assert_eq!(pattern.find_next(&buf, 3, false), Some((6, false)));
+    assert_eq!(pattern.find_next(&buf, 6, false), Some((0, true)));
+}
+#[test]
+fn search_pattern_next_backward() {
+    let buf = Buffer::from(b"ab ab ab".to_vec());
+    let pattern = Pattern::regex("ab", TextOptions::default()).unwrap();
+    assert_eq!(pattern.find_next(&buf, 6, true), Some((3, false)));
+    assert_eq!(pattern.find_next(&buf, 4, true), Some((3, false)));
+    assert_eq!(pattern.find_next(&buf, 0, true), Some((6, true)));
+}
+#[test]
+fn search_pattern_next_hex() {
+    let buf = Buffer::from(vec![0x12, 0x34, 0x12, 0x35]);
+    let pattern = Pattern::hex("123x").unwrap();
+    assert_eq!(pattern.find_next(&buf, 0, false), Some((2, false)));
+    assert_eq!(pattern.find_next(&buf, 2, false), Some((0, true)));
+    assert_eq!(pattern.find_next(&buf, 2, true), Some((0, false)));
+}
+#[test]
+fn search_pattern_next_not_found() {
+    let buf = Buffer::from(vec![0x12, 0x34]);
+    assert_eq!(Pattern::hex("56").unwrap().find_next(&buf, 0, false), None);
+    assert_eq!(
+        Pattern::regex("x", TextOptions::default())
+            .unwrap()
+            .find_next(&buf, 1, true),
+        None
+    );
+}
+#[test]
+fn search_pattern_matches_in_range() {
+    let buf = Buffer::from(b"ab ab ab".to_vec());
+    let pattern = Pattern::regex("ab", TextOptions::default()).unwrap();
+    assert_eq!(pattern.matches_in(&buf, 0..8), vec![0..2, 3..5, 6..8]);
+    // Matches partly inside the range count as well
+    assert_eq!(pattern.matches_in(&buf, 4..6), vec![3..5]);
+    assert!(pattern.matches_in(&buf, 2..3).is_empty());
+}
+#[test]
+fn search_pattern_count_matches() {
+    let buf = Buffer::from(vec![0xAB; 100]);
+    let pattern = Pattern::hex("AB AB").unwrap();
+    assert_eq!(pattern.count_matches(&buf, 3), (50, vec![0, 2, 4]));
+    assert_eq!(pattern.count_matches(&buf, 0), (50, vec![]));
+    let pattern = Pattern::hex("CD").unwrap();
+    assert_eq!(pattern.count_matches(&buf, 16), (0, vec![]));
+}
+#[test]
+fn search_pattern_matches_in_hex_spans_bytes() {
+    let buf = Buffer::from(vec![0x12, 0x34, 0x12, 0x35, 0x12]);
+    let pattern = Pattern::hex("123x").unwrap();
+    assert_eq!(pattern.matches_in(&buf, 0..5), vec![0..2, 2..4]);
+    // A single wildcard nibble at the end makes no byte
+    let pattern = Pattern::hex("12x").unwrap();
+    assert_eq!(pattern.matches_in(&buf, 0..5), vec![0..1, 2..3, 4..5]);
+}
+#[test]
+fn search_regex_on_bytes() {
+    let buf = Buffer::from(b"\x00\x7fELF\x01\x02\x03\xff".to_vec());
+    let pattern = Pattern::regex("\\x7fELF", TextOptions::default()).unwrap();
+    assert_eq!(pattern.find_next(&buf, 0, false), Some((1, false)));
+    let pattern = Pattern::regex("[\\x00-\\x03]{3,}", TextOptions::default()).unwrap();
+    assert_eq!(pattern.matches_in(&buf, 0..9), vec![5..8]);
+    let pattern = Pattern::regex("\\xff", TextOptions::default()).unwrap();
+    assert_eq!(pattern.find_next(&buf, 8, true), Some((8, true)));
+}
+#[test]
+fn search_regex_invalid() {
+    assert!(Pattern::regex("[", TextOptions::default()).is_err());
+}
+#[test]
+fn search_pattern_matches_within_range() {
+    let buf = Buffer::from(vec![0xAA; 8]);
+    let pattern = Pattern::hex("AAAA").unwrap();
+    assert_eq!(pattern.matches_within(&buf, 1..6), vec![1..3, 3..5]);
+    assert_eq!(pattern.matches_in(&buf, 1..6), vec![0..2, 2..4, 4..6]);
+}
+#[test]
+fn search_text_flags() {
+    let options = TextOptions::default();
+    let (flagged, text) = options.strip_flags("\\u16le \\cName");
+    assert_eq!(flagged.encoding, Encoding::Utf16Le);
+    assert!(flagged.ignorecase);
+    assert_eq!(text, "Name");
+    // Unknown words are left to the regular expression
+    assert_eq!(options.strip_flags("\\x41 b"), (options, "\\x41 b"));
+}
+#[test]
+fn search_text_utf16_ignoring_case() {
+    let buf = Buffer::from(b"n\0a\0m\0e\0 N\0A\0M\0E\0".to_vec());
+    let options = TextOptions {
+        encoding: Encoding::Utf16Le,
+        ignorecase: true,
+    };
+    let pattern = Pattern::regex("nAme", options).unwrap();
+    assert_eq!(pattern.matches_in(&buf, 0..buf.len()), vec![0..8, 9..17]);
+    // Special characters are searched for literally
+    let pattern = Pattern::regex(".", options).unwrap();
+    assert!(pattern.matches_in(&buf, 0..buf.len()).is_empty());
+}
+#[test]
+fn search_text_ebcdic() {
+    let buf = Buffer::from(vec![0x00, 0xC8, 0x85, 0x93, 0x93, 0x96]);
+    let options = TextOptions {
+        encoding: Encoding::Ebcdic,
+        ignorecase: false,
+    };
+    let pattern = Pattern::regex("Hello", options).unwrap();
+    assert_eq!(pattern.find_next(&buf, 0, false), Some((1, false)));
+    assert!(Pattern::regex("€", options).is_err());
+}
+#[test]
+fn search_text_ignoring_case() {
+    let buf = Buffer::from(b"xx ELF".to_vec());
+    let options = TextOptions {
+        encoding: Encoding::Utf8,
+        ignorecase: true,
+    };
+    let pattern = Pattern::regex("elf", options).unwrap();
+    assert_eq!(pattern.find_next(&buf, 0, false), Some((3, false)));
+}
+#[test]
+fn search_signature_spaces_and_question_marks() {
+    let buf = [0x0A, 0x3C, 0x1D, 0xEE, 0x0F];
+    assert_eq!(search(&buf, "3C ?? EE".as_bytes()), Some(1));
+    assert_eq!(search(&buf, "1? EE 0".as_bytes()), Some(2));
+}
+#[test]
+fn search_signature_mask() {
+    let buf = [0x0A, 0x3C, 0x47, 0xEE];
+    assert_eq!(search(&buf, "4?&F0".as_bytes()), Some(2));
+    assert_eq!(search(&buf, "40&F0 EE".as_bytes()), Some(2));
+    assert_eq!(search(&buf, "01&01".as_bytes()), Some(2));
+    assert_eq!(search(&buf, "4F&F8".as_bytes()), None);
+}
+#[test]
+fn search_signature_gaps() {
+    let buf = [0xE8, 0x01, 0x02, 0x03, 0x04, 0x48, 0x8B];
+    assert_eq!(search(&buf, "E8 [4] 48 8B".as_bytes()), Some(0));
+    assert_eq!(search(&buf, "E8 [3] 48 8B".as_bytes()), None);
+    assert_eq!(search(&buf, "E8 [2-8] 8B".as_bytes()), Some(0));
+    assert_eq!(search(&buf, "01 [1-] 8B".as_bytes()), Some(1));
+    assert_eq!(search(&buf, "E8 [0-2] 48".as_bytes()), None);
+}
+#[test]
+fn search_signature_open_gap_is_bounded() {
+    let mut data = vec![0; 7000];
+    data[0] = 0xE8;
+    data[3] = 0x48;
+    data[6000] = 0x48;
+    let buf = Buffer::from(data);
+    let pattern = Pattern::hex("E8 [2-] 48").unwrap();
+    assert_eq!(pattern.matches_in(&buf, 0..buf.len()), vec![0..4]);
+    let pattern = Pattern::hex("E8 [4-] 48").unwrap();
+    assert!(pattern.matches_in(&buf, 0..buf.len()).is_empty());
+}
+#[test]
+fn search_signature_alternatives() {
+    let buf = [0x00, 0xBB, 0xCC, 0xDD];
+    assert_eq!(search(&buf, "(AA|BB CC) DD".as_bytes()), Some(1));
+    assert_eq!(search(&buf, "(AA|BB) DD".as_bytes()), None);
+    assert_eq!(search(&buf, "DD|CC".as_bytes()), Some(2));
+}
+#[test]
+fn search_signature_invalid() {
+    assert!(Signature::parse("(AA").is_none());
+    assert!(Signature::parse("AA)").is_none());
+    assert!(Signature::parse("[4").is_none());
+    assert!(Signature::parse("[a]").is_none());
+    assert!(Signature::parse("&F0").is_none());
+    assert!(Signature::parse("AA&G0").is_none());
+    assert!(Signature::parse("").is_none());
+}
+#[test]
+fn search_signature_huge_gap() {
+    assert!(Signature::parse("AA [18446744073709551615] BB").is_none());
+    assert!(Signature::parse("AA [1-18446744073709551615] BB").is_none());
+    assert!(Signature::parse("AA [4097] BB").is_none());
+    assert!(Signature::parse("AA [4096] BB").is_some());
+}
+#[test]
+fn search_signature_length_across_chunks() {
+    let mut data = vec![0; CHUNK + 10];
+    data[CHUNK - 2] = 0xE8;
+    data[CHUNK + 3] = 0x48;
+    let buf = Buffer::from(data);
+    let pattern = Pattern::hex("E8 [2-8] 48").unwrap();
+    assert_eq!(pattern.find_next(&buf, 0, false), Some((CHUNK - 2, false)));
+    let pattern = Pattern::hex("E8 [4] 48").unwrap();
+    assert_eq!(pattern.find_next(&buf, 0, false), Some((CHUNK - 2, false)));
+}
+#[test]
+fn search_rfind_same_as_forward() {
+    let haystack = b"\x00ab\x00\x01 ab\x01xab a";
+    for text in ["ab", "\\x00.*\\x01", "a*", "\\bab", "^ab", "b|\\x01"] {
+        let expression = Expression::new(text, false).unwrap();
+        for starts in 0..=haystack.len() {
+            assert_eq!(
+                expression.rfind(haystack, starts),
+                expression.rfind_forward(haystack, starts),
+                "{} {}",
+                text,
+                starts
+            );
+        }
+    }
+}
+#[test]
+fn search_backward_over_whole_chunk() {
+    let buf = Buffer::from(vec![0; CHUNK + 10]);
+    let pattern = Pattern::regex("\\x00.*\\x01", TextOptions::default()).unwrap();
+    assert_eq!(pattern.find_next(&buf, CHUNK, true), None);
 }
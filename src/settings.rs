@@ -0,0 +1,108 @@
+// Options that can be changed during runtime with ':set'
+
+use super::clipboard::{Backend, ClipFormat};
+use super::encoding::Encoding;
+use std::cmp;
+
+// Bytes shown per row
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Columns {
+    Fixed(usize),
+    // As many as fit into the window
+    Auto,
+}
+
+impl Default for Columns {
+    fn default() -> Self {
+        Columns::Fixed(16)
+    }
+}
+
+// More bytes per row would only make the layout overflow
+const MAX_COLUMNS: usize = 4096;
+
+impl Columns {
+    // A number like "32" or "auto"
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(Columns::Auto),
+            n => n
+                .parse()
+                .ok()
+                .filter(|n| (1..=MAX_COLUMNS).contains(n))
+                .map(Columns::Fixed),
+        }
+    }
+    // The bytes per row, for a window as wide as width
+    pub fn count(&self, width: usize) -> usize {
+        match self {
+            Columns::Fixed(cols) => *cols,
+            // Address, separators and three characters in hex plus one in ascii per byte
+            Columns::Auto => cmp::max(width.saturating_sub(12) / 4, 1),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Settings {
+    // Never change the size of the data, deletes overwrite with zeros
+    pub fixedsize: bool,
+    // Data can not be changed and is only written with ':w!'
+    pub readonly: bool,
+    // Keep the previous content as "file~" when saving
+    pub backup: bool,
+    // How bytes are put on the clipboard, "hex", "raw" or "c"
+    pub clipformat: ClipFormat,
+    // How the clipboard is reached, "osc52", "xclip", "wl-copy" or a program
+    pub clipboard: Backend,
+    // How the text of a search is encoded, E.g. "utf16le" or "ebcdic"
+    pub searchenc: Encoding,
+    // Searches ignore the case of ASCII letters
+    pub ignorecase: bool,
+    // Bytes per row, a number or "auto"
+    pub cols: Columns,
+}
+
+impl Settings {
+    // Applies one option, E.g. "fixedsize", "noreadonly" or "clipformat=c"
+    pub fn set(&mut self, option: &str) -> Result<(), String> {
+        if let Some((name, value)) = option.split_once('=') {
+            return self.set_value(name, value);
+        }
+        match option {
+            "fixedsize" => self.fixedsize = true,
+            "nofixedsize" => self.fixedsize = false,
+            "readonly" | "ro" => self.readonly = true,
+            "noreadonly" | "noro" => self.readonly = false,
+            "backup" | "bk" => self.backup = true,
+            "nobackup" | "nobk" => self.backup = false,
+            "ignorecase" | "ic" => self.ignorecase = true,
+            "noignorecase" | "noic" => self.ignorecase = false,
+            _ => return Err(format!("Unknown option: {}", option)),
+        }
+        Ok(())
+    }
+    fn set_value(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "clipformat" => {
+                self.clipformat = ClipFormat::from_name(value)
+                    .ok_or_else(|| format!("Invalid argument: {}={}", name, value))?;
+            }
+            "clipboard" => self.clipboard = Backend::from_name(value),
+            "cols" => {
+                self.cols = Columns::from_name(value)
+                    .ok_or_else(|| format!("Invalid argument: {}={}", name, value))?;
+            }
+            "searchenc" => {
+                self.searchenc = Encoding::from_name(value)
+                    .ok_or_else(|| format!("Invalid argument: {}={}", name, value))?;
+            }
+            _ => return Err(format!("Unknown option: {}", name)),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[path = "./settings_test.rs"]
+mod settings_test;
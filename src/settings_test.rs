@@ -0,0 +1,92 @@
+use super::*;
+
+#[test]
+fn settings_default() {
+    let settings = Settings::default();
+    assert!(!settings.fixedsize);
+    assert!(!settings.readonly);
+}
+#[test]
+fn settings_set_fixedsize() {
+    let mut settings = Settings::default();
+    assert!(settings.set("fixedsize").is_ok());
+    assert!(settings.fixedsize);
+    assert!(settings.set("nofixedsize").is_ok());
+    assert!(!settings.fixedsize);
+}
+#[test]
+fn settings_set_unknown() {
+    let mut settings = Settings::default();
+    assert!(settings.set("nonsense").is_err());
+}
+#[test]
+fn settings_set_readonly_short() {
+    let mut settings = Settings::default();
+    assert!(settings.set("ro").is_ok());
+    assert!(settings.readonly);
+    assert!(settings.set("noro").is_ok());
+    assert!(!settings.readonly);
+}
+#[test]
+fn settings_set_backup() {
+    let mut settings = Settings::default();
+    assert!(settings.set("backup").is_ok());
+    assert!(settings.backup);
+    assert!(settings.set("nobk").is_ok());
+    assert!(!settings.backup);
+}
+#[test]
+fn settings_set_clipformat() {
+    let mut settings = Settings::default();
+    assert_eq!(settings.clipformat, ClipFormat::Hex);
+    assert!(settings.set("clipformat=c").is_ok());
+    assert_eq!(settings.clipformat, ClipFormat::CArray);
+    assert!(settings.set("clipformat=nonsense").is_err());
+    assert_eq!(settings.clipformat, ClipFormat::CArray);
+}
+#[test]
+fn settings_set_clipboard() {
+    let mut settings = Settings::default();
+    assert_eq!(settings.clipboard, Backend::Osc52);
+    assert!(settings.set("clipboard=wl-copy").is_ok());
+    assert_eq!(
+        settings.clipboard,
+        Backend::Command(vec![String::from("wl-copy")])
+    );
+    assert!(settings.set("nonsense=1").is_err());
+}
+#[test]
+fn settings_set_search_options() {
+    let mut settings = Settings::default();
+    assert_eq!(settings.searchenc, Encoding::Utf8);
+    assert!(settings.set("searchenc=utf16le").is_ok());
+    assert_eq!(settings.searchenc, Encoding::Utf16Le);
+    assert!(settings.set("searchenc=utf7").is_err());
+    assert!(settings.set("ic").is_ok());
+    assert!(settings.ignorecase);
+    assert!(settings.set("noignorecase").is_ok());
+    assert!(!settings.ignorecase);
+}
+#[test]
+fn settings_set_cols() {
+    let mut settings = Settings::default();
+    assert_eq!(settings.cols.count(100), 16);
+    assert!(settings.set("cols=8").is_ok());
+    assert_eq!(settings.cols, Columns::Fixed(8));
+    assert!(settings.set("cols=0").is_err());
+    assert!(settings.set("cols=wide").is_err());
+    assert!(settings.set("cols=4096").is_ok());
+    assert_eq!(settings.cols, Columns::Fixed(4096));
+    assert!(settings.set("cols=4097").is_err());
+    assert!(settings.set("cols=18446744073709551615").is_err());
+    assert_eq!(settings.cols, Columns::Fixed(4096));
+    assert!(settings.set("cols=auto").is_ok());
+    assert_eq!(settings.cols, Columns::Auto);
+}
+#[test]
+fn settings_cols_auto_fits_width() {
+    // 12 characters for the address and separators, 4 per byte
+    assert_eq!(Columns::Auto.count(12 + 4 * 24), 24);
+    assert_eq!(Columns::Auto.count(12 + 4 * 24 - 1), 23);
+    assert_eq!(Columns::Auto.count(5), 1);
+}
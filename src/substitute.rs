@@ -0,0 +1,163 @@
+// Replacing the matches of a search, like ':s/pattern/replacement/' in vim.
+// All matches are found first, so with the flag 'c' they can be stepped
+// through, while the data changes in between.
+
+use super::Buffer;
+use super::History;
+use super::Pattern;
+use std::cmp;
+use std::ops::Range;
+
+// What a match gets replaced with
+#[derive(Clone, PartialEq, Debug)]
+pub enum Replacement {
+    Ascii(Vec<u8>),
+    // Nibbles, wildcards keep the nibble of the match
+    Hex(String),
+}
+
+impl Replacement {
+    // Text like "\x00" stands for the byte, "\/" and "\\" for the character
+    pub fn ascii(text: &str) -> Option<Self> {
+        let mut data = Vec::new();
+        let mut rest = text.as_bytes();
+        while let Some((&c, tail)) = rest.split_first() {
+            rest = tail;
+            if c != b'\\' {
+                data.push(c);
+                continue;
+            }
+            match rest.split_first() {
+                Some((b'x', tail)) => {
+                    let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+                    data.push(u8::from_str_radix(hex, 16).ok()?);
+                    rest = &tail[2..];
+                }
+                Some((&c, tail)) => {
+                    data.push(c);
+                    rest = tail;
+                }
+                None => data.push(b'\\'),
+            }
+        }
+        Some(Replacement::Ascii(data))
+    }
+    // Spaces are left away, like in a hex search
+    pub fn hex(text: &str) -> Self {
+        Replacement::Hex(text.replace(' ', ""))
+    }
+    pub fn len(&self) -> usize {
+        match self {
+            Replacement::Ascii(data) => data.len(),
+            Replacement::Hex(text) => text.len().div_ceil(2),
+        }
+    }
+    pub fn text(&self) -> String {
+        match self {
+            Replacement::Ascii(data) => String::from_utf8_lossy(data).into_owned(),
+            Replacement::Hex(text) => text.clone(),
+        }
+    }
+    // The bytes to put instead of the matched ones
+    pub fn bytes(&self, matched: &[u8]) -> Vec<u8> {
+        match self {
+            Replacement::Ascii(data) => data.clone(),
+            Replacement::Hex(text) => {
+                let nibbles: Vec<Option<u8>> = text
+                    .chars()
+                    .map(|c| c.to_digit(16).map(|n| n as u8))
+                    .collect();
+                (0..self.len())
+                    .map(|i| {
+                        let old = matched.get(i).copied().unwrap_or(0);
+                        let left = nibbles[2 * i].unwrap_or(old >> 4);
+                        let right = match nibbles.get(2 * i + 1) {
+                            Some(Some(n)) => *n,
+                            _ => old & 0x0F,
+                        };
+                        left << 4 | right
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+pub struct Substitute {
+    pub replacement: Replacement,
+    // Where the matches were, before anything got replaced
+    matches: Vec<Range<usize>>,
+    next: usize,
+    // How the replacements so far moved the following data
+    added: usize,
+    removed: usize,
+    pub replaced: usize,
+}
+
+impl Substitute {
+    // Without global, only the first match of every line is replaced
+    pub fn new(
+        pattern: &Pattern,
+        replacement: Replacement,
+        buf: &Buffer,
+        range: Range<usize>,
+        global: bool,
+        cols: usize,
+    ) -> Self {
+        let mut matches = pattern.matches_within(buf, range);
+        if !global {
+            matches.dedup_by_key(|m| m.start / cols);
+        }
+        Substitute {
+            replacement,
+            matches,
+            next: 0,
+            added: 0,
+            removed: 0,
+            replaced: 0,
+        }
+    }
+    // Whether a replacement changes the size of the data
+    pub fn resizes(&self) -> bool {
+        let len = self.replacement.len();
+        self.matches[self.next..].iter().any(|m| m.len() != len)
+    }
+    // Where the next match is now
+    pub fn current(&self) -> Option<Range<usize>> {
+        let m = self.matches.get(self.next)?;
+        let start = m.start + self.added - self.removed;
+        Some(start..start + m.len())
+    }
+    pub fn skip(&mut self) {
+        self.next = cmp::min(self.next + 1, self.matches.len());
+    }
+    // The remaining matches are left as they are
+    pub fn stop(&mut self) {
+        self.next = self.matches.len();
+    }
+    pub fn replace(&mut self, buf: &mut Buffer, history: &mut History) {
+        if let Some(range) = self.current() {
+            let data = self.replacement.bytes(&buf.read(range.clone()));
+            history.splice(buf, range.clone(), &data);
+            self.added += data.len();
+            self.removed += range.len();
+            self.replaced += 1;
+            self.skip();
+        }
+    }
+    pub fn replace_all(&mut self, buf: &mut Buffer, history: &mut History) {
+        while self.current().is_some() {
+            self.replace(buf, history);
+        }
+    }
+    pub fn summary(&self) -> String {
+        match self.replaced {
+            1 => String::from("1 substitution"),
+            n => format!("{} substitutions", n),
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./substitute_test.rs"]
+mod substitute_test;
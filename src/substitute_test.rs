@@ -0,0 +1,112 @@
+use super::*;
+use crate::search::TextOptions;
+use crate::Cursor;
+
+fn substitute(buf: &mut Buffer, pattern: Pattern, replacement: Replacement, global: bool) {
+    let mut history = History::default();
+    let range = 0..buf.len();
+    let mut substitute = Substitute::new(&pattern, replacement, buf, range, global, 4);
+    substitute.replace_all(buf, &mut history);
+}
+
+#[test]
+fn substitute_hex_global() {
+    let mut buf = Buffer::from(vec![0xDE, 0xAD, 0x00, 0xDE, 0xAD, 0xDE, 0xAD]);
+    let pattern = Pattern::hex("DEAD").unwrap();
+    substitute(
+        &mut buf,
+        pattern,
+        Replacement::Hex(String::from("BEEF")),
+        true,
+    );
+    assert_eq!(buf.to_vec(), vec![0xBE, 0xEF, 0x00, 0xBE, 0xEF, 0xBE, 0xEF]);
+}
+#[test]
+fn substitute_first_of_each_line() {
+    let mut buf = Buffer::from(vec![0xAA, 0xAA, 0x00, 0x00, 0xAA, 0xAA]);
+    let pattern = Pattern::hex("AA").unwrap();
+    substitute(
+        &mut buf,
+        pattern,
+        Replacement::Hex(String::from("BB")),
+        false,
+    );
+    assert_eq!(buf.to_vec(), vec![0xBB, 0xAA, 0x00, 0x00, 0xBB, 0xAA]);
+}
+#[test]
+fn substitute_resizes() {
+    let mut buf = Buffer::from(b"a-a-a".to_vec());
+    let pattern = Pattern::regex("a", TextOptions::default()).unwrap();
+    let replacement = Replacement::ascii("xyz").unwrap();
+    substitute(&mut buf, pattern, replacement, true);
+    assert_eq!(buf.to_vec(), b"xyz-xyz-xyz".to_vec());
+    let mut buf = Buffer::from(b"abcabc".to_vec());
+    let pattern = Pattern::regex("bc", TextOptions::default()).unwrap();
+    substitute(&mut buf, pattern, Replacement::ascii("").unwrap(), true);
+    assert_eq!(buf.to_vec(), b"aa".to_vec());
+}
+#[test]
+fn substitute_hex_wildcards_keep_nibbles() {
+    let replacement = Replacement::Hex(String::from("x0Fx"));
+    assert_eq!(replacement.bytes(&[0x12, 0x34]), vec![0x10, 0xF4]);
+    // An odd amount of nibbles ends with a wildcard
+    assert_eq!(
+        Replacement::Hex(String::from("A")).bytes(&[0x12]),
+        vec![0xA2]
+    );
+}
+#[test]
+fn substitute_ascii_escapes() {
+    let replacement = Replacement::ascii("a\\x00\\/\\\\").unwrap();
+    assert_eq!(replacement, Replacement::Ascii(b"a\x00/\\".to_vec()));
+    assert_eq!(Replacement::ascii("\\xZZ"), None);
+}
+#[test]
+fn substitute_step_through() {
+    let mut buf = Buffer::from(b"ab ab ab".to_vec());
+    let mut history = History::default();
+    let pattern = Pattern::regex("ab", TextOptions::default()).unwrap();
+    let replacement = Replacement::ascii("x").unwrap();
+    let mut substitute = Substitute::new(&pattern, replacement, &buf, 0..8, true, 16);
+    assert!(substitute.resizes());
+    substitute.replace(&mut buf, &mut history);
+    assert_eq!(substitute.current(), Some(2..4));
+    substitute.skip();
+    assert_eq!(substitute.current(), Some(5..7));
+    substitute.replace(&mut buf, &mut history);
+    assert_eq!(substitute.current(), None);
+    assert_eq!(buf.to_vec(), b"x ab x".to_vec());
+    assert_eq!(substitute.replaced, 2);
+}
+#[test]
+fn substitute_only_within_range() {
+    let mut buf = Buffer::from(vec![0xAA; 8]);
+    let mut history = History::default();
+    let pattern = Pattern::hex("AAAA").unwrap();
+    let replacement = Replacement::Hex(String::from("0000"));
+    let mut substitute = Substitute::new(&pattern, replacement, &buf, 1..6, true, 16);
+    substitute.replace_all(&mut buf, &mut history);
+    assert_eq!(buf.to_vec(), vec![0xAA, 0, 0, 0, 0, 0xAA, 0xAA, 0xAA]);
+}
+#[test]
+fn substitute_hex_with_spaces() {
+    let replacement = Replacement::hex("42 4? ");
+    assert_eq!(replacement, Replacement::Hex(String::from("424?")));
+    assert_eq!(replacement.bytes(&[0x00, 0x11]), vec![0x42, 0x41]);
+}
+#[test]
+fn substitute_confirmed_in_one_undo_step() {
+    let mut buf = Buffer::from(vec![0xAA, 0x00, 0xAA, 0x00, 0xAA]);
+    let mut history = History::default();
+    let pattern = Pattern::hex("AA").unwrap();
+    let range = 0..buf.len();
+    let replacement = Replacement::hex("BB CC");
+    let mut substitute = Substitute::new(&pattern, replacement, &buf, range, true, 4);
+    substitute.replace(&mut buf, &mut history);
+    substitute.skip();
+    substitute.replace(&mut buf, &mut history);
+    history.close(Cursor::default());
+    assert_eq!(buf.to_vec(), vec![0xBB, 0xCC, 0x00, 0xAA, 0x00, 0xBB, 0xCC]);
+    history.undo(&mut buf);
+    assert_eq!(buf.to_vec(), vec![0xAA, 0x00, 0xAA, 0x00, 0xAA]);
+}
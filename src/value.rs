@@ -0,0 +1,53 @@
+// Encoding of typed values into bytes, to search for them like ':find u32le 1234'.
+// The type is one of u8, i8, u16, i16, u32, i32, u64, i64, f32 and f64,
+// followed by 'le' or 'be' for the byte order. Without it, little endian is used.
+// Integers may also be given in hex, E.g. 0x4D2.
+
+fn parse_int(value: &str) -> Option<i128> {
+    let (negative, digits) = match value.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, value),
+    };
+    let number = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => i128::from_str_radix(hex, 16).ok()?,
+        None => digits.parse().ok()?,
+    };
+    Some(if negative { -number } else { number })
+}
+
+pub fn encode(kind: &str, value: &str) -> Option<Vec<u8>> {
+    let (name, big) = match (kind.strip_suffix("le"), kind.strip_suffix("be")) {
+        (Some(name), _) => (name, false),
+        (_, Some(name)) => (name, true),
+        _ => (kind, false),
+    };
+    let mut bytes = match name {
+        "f32" => value.parse::<f32>().ok()?.to_le_bytes().to_vec(),
+        "f64" => value.parse::<f64>().ok()?.to_le_bytes().to_vec(),
+        _ => {
+            let number = parse_int(value)?;
+            match name {
+                "u8" => u8::try_from(number).ok()?.to_le_bytes().to_vec(),
+                "i8" => i8::try_from(number).ok()?.to_le_bytes().to_vec(),
+                "u16" => u16::try_from(number).ok()?.to_le_bytes().to_vec(),
+                "i16" => i16::try_from(number).ok()?.to_le_bytes().to_vec(),
+                "u32" => u32::try_from(number).ok()?.to_le_bytes().to_vec(),
+                "i32" => i32::try_from(number).ok()?.to_le_bytes().to_vec(),
+                "u64" => u64::try_from(number).ok()?.to_le_bytes().to_vec(),
+                "i64" => i64::try_from(number).ok()?.to_le_bytes().to_vec(),
+                _ => return None,
+            }
+        }
+    };
+    if big {
+        bytes.reverse();
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+#[path = "./value_test.rs"]
+mod value_test;
@@ -0,0 +1,31 @@
+use super::*;
+
+#[test]
+fn value_unsigned() {
+    assert_eq!(encode("u32le", "1234"), Some(vec![0xD2, 0x04, 0x00, 0x00]));
+    assert_eq!(encode("u32be", "1234"), Some(vec![0x00, 0x00, 0x04, 0xD2]));
+    assert_eq!(encode("u16", "0x4D2"), Some(vec![0xD2, 0x04]));
+    assert_eq!(encode("u8", "255"), Some(vec![0xFF]));
+}
+#[test]
+fn value_signed() {
+    assert_eq!(encode("i16be", "-5"), Some(vec![0xFF, 0xFB]));
+    assert_eq!(encode("i8", "-128"), Some(vec![0x80]));
+    assert_eq!(encode("i64le", "-1"), Some(vec![0xFF; 8]));
+}
+#[test]
+fn value_float() {
+    assert_eq!(encode("f32", "1.25"), Some(1.25f32.to_le_bytes().to_vec()));
+    assert_eq!(
+        encode("f64be", "-2.5"),
+        Some((-2.5f64).to_be_bytes().to_vec())
+    );
+}
+#[test]
+fn value_invalid() {
+    assert_eq!(encode("u8", "256"), None);
+    assert_eq!(encode("u16", "-1"), None);
+    assert_eq!(encode("u32", "abc"), None);
+    assert_eq!(encode("u24", "1"), None);
+    assert_eq!(encode("f32", "x"), None);
+}
@@ -0,0 +1,39 @@
+// Visual mode, started by 'v' or 'V'.
+// The selection reaches from where visual mode was started to the cursor,
+// movements extend it and operators act on the selected bytes.
+
+use std::cmp;
+use std::ops::Range;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Visual {
+    // Position where the selection was started
+    pub start: usize,
+    // Whole lines are selected, like 'V'
+    pub lines: bool,
+}
+
+impl Visual {
+    // The selected bytes, the cursor byte is always part of it
+    pub fn range(&self, cursor: usize, cols: usize, len: usize) -> Range<usize> {
+        let mut first = cmp::min(self.start, cursor);
+        let mut last = cmp::max(self.start, cursor) + 1;
+        if self.lines {
+            first -= first % cols;
+            last = last.div_ceil(cols) * cols;
+        }
+        let end = cmp::min(last, len);
+        cmp::min(first, end)..end
+    }
+    pub fn name(&self) -> &'static str {
+        if self.lines {
+            "-- VISUAL LINE --"
+        } else {
+            "-- VISUAL --"
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./visual_test.rs"]
+mod visual_test;
@@ -0,0 +1,41 @@
+use super::*;
+
+#[test]
+fn visual_range_forward() {
+    let visual = Visual {
+        start: 3,
+        lines: false,
+    };
+    assert_eq!(visual.range(5, 16, 100), 3..6);
+}
+#[test]
+fn visual_range_backward() {
+    let visual = Visual {
+        start: 5,
+        lines: false,
+    };
+    assert_eq!(visual.range(2, 16, 100), 2..6);
+}
+#[test]
+fn visual_range_lines() {
+    let visual = Visual {
+        start: 20,
+        lines: true,
+    };
+    assert_eq!(visual.range(5, 16, 100), 0..32);
+    assert_eq!(visual.range(40, 16, 100), 16..48);
+}
+#[test]
+fn visual_range_clipped_to_data() {
+    let visual = Visual {
+        start: 20,
+        lines: true,
+    };
+    assert_eq!(visual.range(25, 16, 24), 16..24);
+    let visual = Visual {
+        start: 3,
+        lines: false,
+    };
+    // The cursor may be behind the data, E.g. in an empty buffer
+    assert_eq!(visual.range(3, 16, 0), 0..0);
+}
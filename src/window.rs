@@ -0,0 +1,246 @@
+// Windows are views onto the documents, the screen can be split into
+// several of them. Every window has its own cursor and scroll position,
+// several windows may show the same document.
+// The arrangement of the windows is kept as a tree of splits, which is
+// turned into rectangles on the screen whenever it is drawn.
+
+use super::Cursor;
+use super::Document;
+
+#[derive(Copy, Clone, Default)]
+pub struct Window {
+    // Index of the document shown
+    pub doc: usize,
+    pub cursor: Cursor,
+    // 0 = display data from first line of file
+    pub screenoffset: usize,
+    // Compared with the other diff window, see src/diff.rs
+    pub diff: bool,
+}
+
+impl Window {
+    // Shows another document, the document left remembers the cursor and
+    // scroll position
+    pub fn show(&mut self, docs: &mut [Document], index: usize) {
+        if let Some(doc) = docs.get_mut(self.doc) {
+            doc.cursor = self.cursor;
+            doc.screenoffset = self.screenoffset;
+        }
+        self.doc = index;
+        self.cursor = docs[index].cursor;
+        self.screenoffset = docs[index].screenoffset;
+    }
+    // Moves the screen when the cursor leaves it
+    pub fn scroll_to_cursor(&mut self, rows: usize, cols: usize) {
+        let line = self.cursor.pos() / cols;
+        if line >= self.screenoffset + rows {
+            self.screenoffset = (line + 1).saturating_sub(rows);
+        }
+        if line < self.screenoffset {
+            self.screenoffset = line;
+        }
+    }
+}
+
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    // Windows only get a status line of their own when the screen is split
+    pub fn text_rows(&self, split: bool) -> usize {
+        if split {
+            self.height.saturating_sub(1)
+        } else {
+            self.height
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Split {
+    // Windows above each other, like ':split'
+    Horizontal,
+    // Windows next to each other, like ':vsplit'
+    Vertical,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Direction {
+    Left,
+    Down,
+    Up,
+    Right,
+}
+
+enum Node {
+    Window(usize),
+    Split(Split, Vec<Node>),
+}
+
+pub struct Layout {
+    root: Node,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout {
+            root: Node::Window(0),
+        }
+    }
+}
+
+impl Layout {
+    // The new window is placed above or left of the split window
+    pub fn split(&mut self, window: usize, new: usize, split: Split) {
+        split_node(&mut self.root, window, new, split);
+    }
+    // Windows with a higher number than the closed one move down by one
+    pub fn close(&mut self, window: usize) {
+        close_node(&mut self.root, window);
+    }
+    // The rectangles of the windows, indexed by window
+    pub fn rects(&self, area: Rect) -> Vec<Rect> {
+        let mut placed = Vec::new();
+        place(&self.root, area, &mut placed);
+        placed.sort_by_key(|(window, _)| *window);
+        placed.into_iter().map(|(_, rect)| rect).collect()
+    }
+}
+
+fn is_window(node: &Node, window: usize) -> bool {
+    matches!(node, Node::Window(id) if *id == window)
+}
+
+fn split_node(node: &mut Node, window: usize, new: usize, split: Split) -> bool {
+    match node {
+        Node::Window(id) if *id == window => {
+            *node = Node::Split(split, vec![Node::Window(new), Node::Window(window)]);
+            true
+        }
+        Node::Window(_) => false,
+        Node::Split(dir, children) => {
+            // Splitting in the same direction again adds a sibling
+            if *dir == split {
+                if let Some(i) = children.iter().position(|c| is_window(c, window)) {
+                    children.insert(i, Node::Window(new));
+                    return true;
+                }
+            }
+            children
+                .iter_mut()
+                .any(|c| split_node(c, window, new, split))
+        }
+    }
+}
+
+fn close_node(node: &mut Node, window: usize) {
+    match node {
+        Node::Window(id) => {
+            if *id > window {
+                *id -= 1;
+            }
+        }
+        Node::Split(_, children) => {
+            children.retain(|c| !is_window(c, window));
+            for child in children.iter_mut() {
+                close_node(child, window);
+            }
+            // A split of a single window is no split anymore
+            if children.len() == 1 {
+                let only = children.remove(0);
+                *node = only;
+            }
+        }
+    }
+}
+
+fn place(node: &Node, area: Rect, placed: &mut Vec<(usize, Rect)>) {
+    match node {
+        Node::Window(id) => placed.push((*id, area)),
+        Node::Split(Split::Horizontal, children) => {
+            let height = area.height / children.len();
+            let mut y = area.y;
+            for (i, child) in children.iter().enumerate() {
+                // The last window gets what is left over
+                let h = if i + 1 == children.len() {
+                    (area.y + area.height).saturating_sub(y)
+                } else {
+                    height
+                };
+                place(
+                    child,
+                    Rect {
+                        y,
+                        height: h,
+                        ..area
+                    },
+                    placed,
+                );
+                y += h;
+            }
+        }
+        Node::Split(Split::Vertical, children) => {
+            // One column between the windows is used as separator
+            let separators = children.len() - 1;
+            let width = area.width.saturating_sub(separators) / children.len();
+            let mut x = area.x;
+            for (i, child) in children.iter().enumerate() {
+                let w = if i + 1 == children.len() {
+                    (area.x + area.width).saturating_sub(x)
+                } else {
+                    width
+                };
+                place(
+                    child,
+                    Rect {
+                        x,
+                        width: w,
+                        ..area
+                    },
+                    placed,
+                );
+                x += w + 1;
+            }
+        }
+    }
+}
+
+// The other window the window is compared with in diff mode
+pub fn diff_partner(windows: &[Window], window: usize) -> Option<usize> {
+    if !windows.get(window)?.diff {
+        return None;
+    }
+    (0..windows.len()).find(|&i| i != window && windows[i].diff)
+}
+
+// The window next to the given one in the direction, like 'Ctrl-W l'.
+// Of several windows, the one closest to the top left corner is taken.
+pub fn neighbour(rects: &[Rect], window: usize, direction: Direction) -> Option<usize> {
+    let a = rects.get(window)?;
+    let overlaps_rows = |r: &Rect| r.y < a.y + a.height && a.y < r.y + r.height;
+    let overlaps_cols = |r: &Rect| r.x < a.x + a.width && a.x < r.x + r.width;
+    rects
+        .iter()
+        .enumerate()
+        .filter_map(|(i, r)| {
+            let distance = match direction {
+                Direction::Left if overlaps_rows(r) && r.x + r.width <= a.x => a.x - r.x,
+                Direction::Right if overlaps_rows(r) && r.x >= a.x + a.width => r.x - a.x,
+                Direction::Up if overlaps_cols(r) && r.y + r.height <= a.y => a.y - r.y,
+                Direction::Down if overlaps_cols(r) && r.y >= a.y + a.height => r.y - a.y,
+                _ => return None,
+            };
+            Some((distance, r.y, r.x, i))
+        })
+        .min()
+        .map(|(_, _, _, i)| i)
+}
+
+#[cfg(test)]
+#[path = "./window_test.rs"]
+mod window_test;
@@ -0,0 +1,115 @@
+use super::*;
+
+const AREA: Rect = Rect {
+    x: 0,
+    y: 0,
+    width: 81,
+    height: 20,
+};
+
+#[test]
+fn window_layout_single() {
+    let layout = Layout::default();
+    assert_eq!(layout.rects(AREA), vec![AREA]);
+}
+#[test]
+fn window_layout_split_horizontal() {
+    let mut layout = Layout::default();
+    layout.split(0, 1, Split::Horizontal);
+    let rects = layout.rects(AREA);
+    assert_eq!(rects[1], Rect { height: 10, ..AREA });
+    assert_eq!(
+        rects[0],
+        Rect {
+            y: 10,
+            height: 10,
+            ..AREA
+        }
+    );
+}
+#[test]
+fn window_layout_split_vertical_leaves_separator() {
+    let mut layout = Layout::default();
+    layout.split(0, 1, Split::Vertical);
+    let rects = layout.rects(AREA);
+    assert_eq!(rects[1], Rect { width: 40, ..AREA });
+    assert_eq!(
+        rects[0],
+        Rect {
+            x: 41,
+            width: 40,
+            ..AREA
+        }
+    );
+}
+#[test]
+fn window_layout_split_same_direction_adds_sibling() {
+    let mut layout = Layout::default();
+    layout.split(0, 1, Split::Horizontal);
+    layout.split(1, 2, Split::Horizontal);
+    let rects = layout.rects(AREA);
+    assert_eq!(rects[2].y, 0);
+    assert_eq!(rects[1].y, 6);
+    assert_eq!(rects[0].y, 12);
+    assert_eq!(rects[0].height, 8);
+}
+#[test]
+fn window_layout_close_renumbers() {
+    let mut layout = Layout::default();
+    layout.split(0, 1, Split::Vertical);
+    layout.split(1, 2, Split::Horizontal);
+    layout.close(0);
+    let rects = layout.rects(AREA);
+    assert_eq!(rects.len(), 2);
+    assert_eq!(rects[1], Rect { height: 10, ..AREA });
+    layout.close(1);
+    assert_eq!(layout.rects(AREA), vec![AREA]);
+}
+#[test]
+fn window_neighbour() {
+    let mut layout = Layout::default();
+    layout.split(0, 1, Split::Vertical);
+    layout.split(1, 2, Split::Horizontal);
+    // 2 is top left, 1 bottom left and 0 is right
+    let rects = layout.rects(AREA);
+    assert_eq!(neighbour(&rects, 2, Direction::Right), Some(0));
+    assert_eq!(neighbour(&rects, 2, Direction::Down), Some(1));
+    assert_eq!(neighbour(&rects, 1, Direction::Up), Some(2));
+    assert_eq!(neighbour(&rects, 0, Direction::Left), Some(2));
+    assert_eq!(neighbour(&rects, 0, Direction::Right), None);
+}
+#[test]
+fn window_scroll_to_cursor() {
+    let mut window = Window::default();
+    window.cursor.set_pos(16 * 12);
+    window.scroll_to_cursor(10, 16);
+    assert_eq!(window.screenoffset, 3);
+    window.cursor.set_pos(16);
+    window.scroll_to_cursor(10, 16);
+    assert_eq!(window.screenoffset, 1);
+}
+#[test]
+fn window_diff_partner() {
+    let mut windows = vec![Window::default(); 3];
+    assert_eq!(diff_partner(&windows, 0), None);
+    windows[0].diff = true;
+    assert_eq!(diff_partner(&windows, 0), None);
+    windows[2].diff = true;
+    assert_eq!(diff_partner(&windows, 0), Some(2));
+    assert_eq!(diff_partner(&windows, 2), Some(0));
+    assert_eq!(diff_partner(&windows, 1), None);
+}
+#[test]
+fn window_show_restores_scroll_position() {
+    let mut docs = vec![Document::default(), Document::default()];
+    let mut win = Window {
+        screenoffset: 5,
+        ..Default::default()
+    };
+    win.show(&mut docs, 1);
+    assert_eq!(win.screenoffset, 0);
+    win.screenoffset = 2;
+    win.show(&mut docs, 0);
+    assert_eq!(win.screenoffset, 5);
+    assert_eq!(docs[1].screenoffset, 2);
+}